@@ -12,6 +12,7 @@ use zip::{write::SimpleFileOptions, ZipWriter};
 
 use crate::{
     backup::{SaveUnit, SaveUnitType},
+    config::app_paths,
     errors::{BackupFileError, CompressError},
     ipc_handler::{IpcNotification, NotificationLevel},
 };
@@ -71,7 +72,7 @@ where
     Ok(())
 }
 
-/// Compress a set of save to a zip file in `backup_path` with name 'date.zip'
+/// Compress a set of save to a zip file in `backup_path` with name 'id.zip'
 pub fn compress_to_file(save_paths: &[SaveUnit], zip_path: &Path) -> Result<(), CompressError> {
     let file = File::create(zip_path).map_err(|e| CompressError::Single(e.into()))?;
     let mut zip = ZipWriter::new(file);
@@ -124,14 +125,14 @@ pub fn compress_to_file(save_paths: &[SaveUnit], zip_path: &Path) -> Result<(),
 pub fn decompress_from_file(
     save_paths: &[SaveUnit],
     backup_path: &Path,
-    date: &str,
+    id: &str,
     app_handle: Option<&AppHandle>,
 ) -> Result<(), CompressError> {
-    let zip_path = backup_path.join([date, ".zip"].concat());
+    let zip_path = backup_path.join([id, ".zip"].concat());
     let file = File::open(zip_path).map_err(|e| CompressError::Single(e.into()))?;
     let mut zip = zip::ZipArchive::new(file).map_err(|e| CompressError::Single(e.into()))?;
 
-    let tmp_folder = PathBuf::from("./tmp"); //TODO: tmp dir
+    let tmp_folder = app_paths().tmp_dir();
     fs::create_dir_all(&tmp_folder).map_err(|e| CompressError::Single(e.into()))?;
     zip.extract(&tmp_folder)
         .map_err(|e| CompressError::Single(e.into()))?;
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{GameSnapshots, Snapshot};
+
+/// A line of snapshots that follow each other
+///
+/// A snapshot continues the branch of its parent when it is the oldest child,
+/// younger children start new branches forked from the parent
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SnapshotBranch {
+    /// 分支中第一个存档的 id
+    pub id: String,
+    pub name: Option<String>,
+    /// 分出此分支的存档，根分支为空
+    pub fork_point: Option<String>,
+    /// 分支中的存档，按时间从旧到新
+    pub snapshots: Vec<String>,
+    pub children: Vec<SnapshotBranch>,
+}
+
+impl SnapshotBranch {
+    fn find(&self, id: &str) -> Option<&SnapshotBranch> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|x| x.find(id))
+    }
+}
+
+impl GameSnapshots {
+    /// The branches of the game as a tree, roots ordered by time
+    pub fn branches(&self) -> Vec<SnapshotBranch> {
+        let mut order: Vec<usize> = (0..self.backups.len()).collect();
+        order.sort_by_key(|&i| (self.backups[i].timestamp, &self.backups[i].id));
+
+        // 父存档不存在时，存档是新的根分支
+        let mut children: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut roots = Vec::new();
+        for &i in &order {
+            let snapshot = &self.backups[i];
+            match snapshot
+                .parent
+                .as_deref()
+                .filter(|p| *p != snapshot.id && self.find(p).is_some())
+            {
+                Some(parent) => children.entry(parent).or_default().push(i),
+                None => roots.push(i),
+            }
+        }
+
+        let mut visited = vec![false; self.backups.len()];
+        let mut branches = Vec::new();
+        for i in roots {
+            branches.push(self.build_branch(i, None, &children, &mut visited));
+        }
+        // 损坏的记录中父存档可能成环，从其中最旧的存档开始
+        for i in order {
+            if !visited[i] {
+                branches.push(self.build_branch(i, None, &children, &mut visited));
+            }
+        }
+        branches
+    }
+
+    fn build_branch(
+        &self,
+        start: usize,
+        fork_point: Option<String>,
+        children: &HashMap<&str, Vec<usize>>,
+        visited: &mut [bool],
+    ) -> SnapshotBranch {
+        let id = self.backups[start].id.clone();
+        let mut snapshots = Vec::new();
+        let mut forks = Vec::new();
+        let mut current = Some(start);
+        while let Some(i) = current.take() {
+            visited[i] = true;
+            let snapshot = &self.backups[i];
+            snapshots.push(snapshot.id.clone());
+            let next: Vec<usize> = children
+                .get(snapshot.id.as_str())
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&x| !visited[x])
+                .collect();
+            if let Some((&first, rest)) = next.split_first() {
+                current = Some(first);
+                forks.extend(rest.iter().map(|&x| (snapshot.id.clone(), x)));
+            }
+        }
+        let mut branch = SnapshotBranch {
+            name: self.branch_names.get(&id).cloned(),
+            id,
+            fork_point,
+            snapshots,
+            children: Vec::new(),
+        };
+        for (fork_point, i) in forks {
+            if !visited[i] {
+                let child = self.build_branch(i, Some(fork_point), children, visited);
+                branch.children.push(child);
+            }
+        }
+        branch
+    }
+
+    /// 分支中的存档，按时间从旧到新，不包含分出此分支之前的存档
+    pub fn branch_snapshots(&self, branch: &str) -> Option<Vec<&Snapshot>> {
+        let branches = self.branches();
+        let branch = branches.iter().find_map(|x| x.find(branch))?;
+        Some(
+            branch
+                .snapshots
+                .iter()
+                .filter_map(|id| self.find(id))
+                .collect(),
+        )
+    }
+
+    /// 设置分支的名称，名称为空时删除，分支不存在时返回 `false`
+    pub fn set_branch_name(&mut self, branch: &str, name: &str) -> bool {
+        if !self.branches().iter().any(|x| x.find(branch).is_some()) {
+            return false;
+        }
+        let name = name.trim();
+        if name.is_empty() {
+            self.branch_names.remove(branch);
+        } else {
+            self.branch_names
+                .insert(branch.to_string(), name.to_string());
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backup::SnapshotStorage;
+
+    fn snapshot(id: &str, parent: Option<&str>) -> Snapshot {
+        Snapshot {
+            parent: parent.map(String::from),
+            ..Snapshot::fixture(id, SnapshotStorage::Local)
+        }
+    }
+
+    #[test]
+    fn branches_from_parents() {
+        // 恢复 01 后继续游玩，得到 01 -> 04 的新分支
+        let mut info = GameSnapshots::new("Game");
+        info.backups = vec![
+            snapshot("2024-01-01_00-00-00", None),
+            snapshot("2024-01-02_00-00-00", Some("2024-01-01_00-00-00")),
+            snapshot("2024-01-03_00-00-00", Some("2024-01-02_00-00-00")),
+            snapshot("2024-01-04_00-00-00", Some("2024-01-01_00-00-00")),
+            snapshot("2024-01-05_00-00-00", Some("2024-01-04_00-00-00")),
+        ];
+        assert!(info.set_branch_name("2024-01-04_00-00-00", " Second run "));
+        assert!(!info.set_branch_name("2024-01-05_00-00-00", "Not a branch"));
+
+        let branches = info.branches();
+        assert_eq!(branches.len(), 1);
+        assert_eq!(
+            branches[0].snapshots,
+            vec![
+                "2024-01-01_00-00-00",
+                "2024-01-02_00-00-00",
+                "2024-01-03_00-00-00"
+            ]
+        );
+        let child = &branches[0].children[0];
+        assert_eq!(child.name.as_deref(), Some("Second run"));
+        assert_eq!(child.fork_point.as_deref(), Some("2024-01-01_00-00-00"));
+        let ids: Vec<_> = info
+            .branch_snapshots("2024-01-04_00-00-00")
+            .unwrap()
+            .iter()
+            .map(|x| x.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["2024-01-04_00-00-00", "2024-01-05_00-00-00"]);
+
+        // 删除分支的第一个存档后，名称和后续的存档都接到下一个存档上
+        info.head = Some("2024-01-04_00-00-00".to_string());
+        info.remove(&["2024-01-04_00-00-00"]);
+        assert_eq!(info.head.as_deref(), Some("2024-01-01_00-00-00"));
+        assert_eq!(
+            info.branch_names
+                .get("2024-01-05_00-00-00")
+                .map(String::as_str),
+            Some("Second run")
+        );
+        let child = &info.branches()[0].children[0];
+        assert_eq!(child.snapshots, vec!["2024-01-05_00-00-00"]);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File};
+
+use serde::Serialize;
+use tracing::{info, warn};
+use zip::ZipArchive;
+
+use crate::cloud_sync::RemoteLayout;
+use crate::config::get_config;
+use crate::errors::BackupError;
+
+use super::reindex::snapshots_on_disk;
+use super::{reindex_game, Game, GameSnapshots, SnapshotStorage};
+
+/// A problem found by `check_repository`
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RepositoryIssue {
+    /// 配置中的游戏没有备份目录
+    MissingGameFolder { game: String },
+    /// 备份路径下不属于任何游戏的目录
+    OrphanFolder { folder: String },
+    /// `Backups.json` 无法读取
+    UnreadableSnapshotsInfo { game: String, error: String },
+    /// 记录中有本地副本的存档，压缩包已不存在
+    MissingArchive { game: String, id: String },
+    /// 压缩包没有对应的记录
+    UnlistedArchive { game: String, id: String },
+    /// 压缩包已损坏，无法打开
+    UnreadableArchive {
+        game: String,
+        id: String,
+        error: String,
+    },
+    /// 同一 id 在记录中出现了多次
+    DuplicateId { game: String, id: String },
+}
+
+impl RepositoryIssue {
+    /// 是否为同一个问题，错误信息可能在重新检查时变化，不参与比较
+    fn same_as(&self, other: &RepositoryIssue) -> bool {
+        use RepositoryIssue::*;
+        match (self, other) {
+            (UnreadableSnapshotsInfo { game: a, .. }, UnreadableSnapshotsInfo { game: b, .. }) => {
+                a == b
+            }
+            (
+                UnreadableArchive {
+                    game: a, id: a_id, ..
+                },
+                UnreadableArchive {
+                    game: b, id: b_id, ..
+                },
+            ) => a == b && a_id == b_id,
+            _ => self == other,
+        }
+    }
+}
+
+/// How an issue can be fixed without asking the user
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FixAction {
+    /// 从记录中删除该存档，重复的 id 只保留第一条
+    RemoveEntry,
+    /// 按磁盘上的压缩包重建记录，见 `reindex_game`
+    Reindex,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Finding {
+    pub issue: RepositoryIssue,
+    /// 为空时需要用户自行处理，如损坏的压缩包和多余的目录
+    pub fix: Option<FixAction>,
+    pub fixed: bool,
+}
+
+impl Finding {
+    fn new(issue: RepositoryIssue, fix: Option<FixAction>) -> Self {
+        Finding {
+            issue,
+            fix,
+            fixed: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RepositoryReport {
+    pub games_checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl RepositoryReport {
+    /// 是否还有未修复的问题
+    pub fn has_problems(&self) -> bool {
+        self.findings.iter().any(|x| !x.fixed)
+    }
+}
+
+/// 比较存档记录和磁盘上的压缩包
+fn check_snapshots(info: &GameSnapshots, on_disk: &BTreeSet<String>) -> Vec<Finding> {
+    let game = &info.name;
+    let mut findings = Vec::new();
+    let mut seen = HashSet::new();
+    for snapshot in &info.backups {
+        if !seen.insert(&snapshot.id) {
+            findings.push(Finding::new(
+                RepositoryIssue::DuplicateId {
+                    game: game.clone(),
+                    id: snapshot.id.clone(),
+                },
+                Some(FixAction::RemoveEntry),
+            ));
+            continue;
+        }
+        if on_disk.contains(&snapshot.id) {
+            continue;
+        }
+        // 仅在云端的存档本来就没有压缩包，云端也有副本的改为仅在云端
+        let fix = match snapshot.storage {
+            SnapshotStorage::Cloud => continue,
+            SnapshotStorage::Both => FixAction::Reindex,
+            SnapshotStorage::Local => FixAction::RemoveEntry,
+        };
+        findings.push(Finding::new(
+            RepositoryIssue::MissingArchive {
+                game: game.clone(),
+                id: snapshot.id.clone(),
+            },
+            Some(fix),
+        ));
+    }
+    for id in on_disk {
+        if !seen.contains(id) {
+            findings.push(Finding::new(
+                RepositoryIssue::UnlistedArchive {
+                    game: game.clone(),
+                    id: id.clone(),
+                },
+                Some(FixAction::Reindex),
+            ));
+        }
+    }
+    findings
+}
+
+/// 删除重复的记录和压缩包已不存在的本地存档
+fn remove_broken_entries(info: &mut GameSnapshots, on_disk: &BTreeSet<String>) {
+    let mut seen = HashSet::new();
+    info.backups.retain(|x| seen.insert(x.id.clone()));
+    let missing: Vec<String> = info
+        .backups
+        .iter()
+        .filter(|x| x.storage == SnapshotStorage::Local && !on_disk.contains(&x.id))
+        .map(|x| x.id.clone())
+        .collect();
+    info.remove(&missing.iter().map(String::as_str).collect::<Vec<_>>());
+}
+
+fn check_game(game: &Game, layout: &RemoteLayout) -> Result<Vec<Finding>, BackupError> {
+    let game_dir = layout.local_game_dir(&game.name);
+    if !game_dir.is_dir() {
+        return Ok(vec![Finding::new(
+            RepositoryIssue::MissingGameFolder {
+                game: game.name.clone(),
+            },
+            Some(FixAction::Reindex),
+        )]);
+    }
+    let on_disk = snapshots_on_disk(&game_dir)?;
+    let mut findings = Vec::new();
+    match fs::read(layout.local_snapshots_info(&game.name)) {
+        Ok(data) => match GameSnapshots::from_slice(&data) {
+            Ok(info) => findings.extend(check_snapshots(&info, &on_disk)),
+            Err(e) => findings.push(Finding::new(
+                RepositoryIssue::UnreadableSnapshotsInfo {
+                    game: game.name.clone(),
+                    error: e.to_string(),
+                },
+                Some(FixAction::Reindex),
+            )),
+        },
+        Err(e) => findings.push(Finding::new(
+            RepositoryIssue::UnreadableSnapshotsInfo {
+                game: game.name.clone(),
+                error: e.to_string(),
+            },
+            Some(FixAction::Reindex),
+        )),
+    }
+    // 只读取压缩包的目录，不校验每个文件的内容
+    for id in &on_disk {
+        let result = File::open(layout.local_snapshot(&game.name, id))
+            .map_err(|e| e.to_string())
+            .and_then(|file| ZipArchive::new(file).map_err(|e| e.to_string()));
+        if let Err(error) = result {
+            findings.push(Finding::new(
+                RepositoryIssue::UnreadableArchive {
+                    game: game.name.clone(),
+                    id: id.clone(),
+                    error,
+                },
+                None,
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+/// 按顺序执行一个游戏的修复：先删除错误的记录，再重建
+async fn fix_game(
+    game: &Game,
+    layout: &RemoteLayout,
+    findings: &mut [Finding],
+) -> Result<(), BackupError> {
+    if findings
+        .iter()
+        .any(|x| x.fix == Some(FixAction::RemoveEntry))
+    {
+        let on_disk = snapshots_on_disk(&layout.local_game_dir(&game.name))?;
+        let _lock = game.lock_snapshots_info().await;
+        let mut info = game.get_game_snapshots_info()?;
+        remove_broken_entries(&mut info, &on_disk);
+        game.set_game_snapshots_info(&info)?;
+    }
+    if findings.iter().any(|x| x.fix == Some(FixAction::Reindex)) {
+        reindex_game(game, false).await?;
+    }
+    // 重新检查，只有不再出现的问题才算已修复
+    let remaining = check_game(game, layout)?;
+    for finding in findings.iter_mut().filter(|x| x.fix.is_some()) {
+        finding.fixed = !remaining.iter().any(|x| x.issue.same_as(&finding.issue));
+    }
+    Ok(())
+}
+
+/// Check every game in the config against its backup folder
+///
+/// With `fix` set, the issues that have a `FixAction` are fixed in place.
+/// Broken archives and folders of unknown games are only reported
+pub async fn check_repository(fix: bool) -> Result<RepositoryReport, BackupError> {
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+    let mut report = RepositoryReport {
+        games_checked: config.games.len(),
+        ..Default::default()
+    };
+
+    for game in &config.games {
+        let mut findings = check_game(game, &layout)?;
+        if fix && findings.iter().any(|x| x.fix.is_some()) {
+            if let Err(e) = fix_game(game, &layout, &mut findings).await {
+                warn!(target:"rgsm::backup::check","Failed to fix {}: {:?}", game.name, e);
+            }
+        }
+        report.findings.extend(findings);
+    }
+
+    let names: HashSet<&str> = config.games.iter().map(|g| g.name.as_str()).collect();
+    if layout.local_root().is_dir() {
+        for entry in fs::read_dir(layout.local_root())? {
+            let entry = entry?;
+            let folder = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() && !names.contains(folder.as_str()) {
+                report
+                    .findings
+                    .push(Finding::new(RepositoryIssue::OrphanFolder { folder }, None));
+            }
+        }
+    }
+    info!(target:"rgsm::backup::check","Checked repository: {:?}", report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backup::Snapshot;
+
+    #[test]
+    fn find_and_remove_broken_entries() {
+        let mut info = GameSnapshots::new("Game");
+        info.backups = vec![
+            Snapshot::fixture("2024-01-01_00-00-00", SnapshotStorage::Local),
+            Snapshot::fixture("2024-01-01_00-00-00", SnapshotStorage::Local),
+            Snapshot::fixture("2024-01-02_00-00-00", SnapshotStorage::Local),
+            Snapshot::fixture("2024-01-03_00-00-00", SnapshotStorage::Both),
+            Snapshot::fixture("2024-01-04_00-00-00", SnapshotStorage::Cloud),
+        ];
+        let on_disk = ["2024-01-01_00-00-00", "2024-01-05_00-00-00"]
+            .map(String::from)
+            .into();
+        let issues: Vec<_> = check_snapshots(&info, &on_disk)
+            .into_iter()
+            .map(|x| (x.issue, x.fix))
+            .collect();
+        let game = || "Game".to_string();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    RepositoryIssue::DuplicateId {
+                        game: game(),
+                        id: "2024-01-01_00-00-00".to_string()
+                    },
+                    Some(FixAction::RemoveEntry)
+                ),
+                (
+                    RepositoryIssue::MissingArchive {
+                        game: game(),
+                        id: "2024-01-02_00-00-00".to_string()
+                    },
+                    Some(FixAction::RemoveEntry)
+                ),
+                (
+                    RepositoryIssue::MissingArchive {
+                        game: game(),
+                        id: "2024-01-03_00-00-00".to_string()
+                    },
+                    Some(FixAction::Reindex)
+                ),
+                (
+                    RepositoryIssue::UnlistedArchive {
+                        game: game(),
+                        id: "2024-01-05_00-00-00".to_string()
+                    },
+                    Some(FixAction::Reindex)
+                ),
+            ]
+        );
+
+        // 有云端副本的存档留给 `reindex_game` 处理
+        remove_broken_entries(&mut info, &on_disk);
+        let ids: Vec<_> = info.backups.iter().map(|x| x.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "2024-01-01_00-00-00",
+                "2024-01-03_00-00-00",
+                "2024-01-04_00-00-00"
+            ]
+        );
+    }
+}
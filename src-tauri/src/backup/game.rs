@@ -1,17 +1,24 @@
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
 
-use crate::cloud_sync::{upload_config, upload_game_snapshots};
-use crate::config::{get_config, set_config};
+use crate::cloud_sync::{read_object, sync_later, PendingOperation, RemoteLayout};
+use crate::config::{get_config, update_config};
 use crate::errors::BackupError;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::{fs, path};
-use tracing::{error, info};
+use tokio::sync::OwnedMutexGuard;
+use tracing::{error, info, warn};
 
+use super::expired_snapshots_per_profile;
 use super::GameSnapshots;
 use super::SaveUnit;
-use super::Snapshot;
 use super::{compress_to_file, decompress_from_file};
+use super::{Snapshot, SnapshotStorage, SnapshotTrigger};
+
+/// 每个游戏一把锁，`Backups.json` 的读-改-写需要持有对应游戏的锁
+static SNAPSHOTS_INFO_LOCKS: Mutex<BTreeMap<String, Arc<tauri::async_runtime::Mutex<()>>>> =
+    Mutex::new(BTreeMap::new());
 
 /// A game struct contains the save units and the game's launcher
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,19 +29,28 @@ pub struct Game {
 }
 
 impl Game {
+    /// Lock the `Backups.json` of this game until the guard is dropped
+    ///
+    /// 不可重入，持有锁时不能调用会再次加锁的方法
+    pub async fn lock_snapshots_info(&self) -> OwnedMutexGuard<()> {
+        let lock = SNAPSHOTS_INFO_LOCKS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(self.name.clone())
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
     pub fn get_game_snapshots_info(&self) -> Result<GameSnapshots, BackupError> {
         let config = get_config()?;
-        let backup_path = path::Path::new(&config.backup_path)
-            .join(&self.name)
-            .join("Backups.json");
-        let backup_info = serde_json::from_slice(&fs::read(backup_path)?)?;
+        let backup_path = RemoteLayout::from_config(&config).local_snapshots_info(&self.name);
+        let backup_info = GameSnapshots::from_slice(&fs::read(backup_path)?)?;
         Ok(backup_info)
     }
     pub fn set_game_snapshots_info(&self, new_info: &GameSnapshots) -> Result<(), BackupError> {
+        new_info.check_writable()?;
         let config = get_config()?;
-        let saves_path = path::Path::new(&config.backup_path)
-            .join(&self.name)
-            .join("Backups.json");
+        let saves_path = RemoteLayout::from_config(&config).local_snapshots_info(&self.name);
         // 处理文件夹不存在的情况，一般发生在初次下载云存档时
         let prefix_root = saves_path.parent().ok_or(BackupError::NonePathError)?;
         if !prefix_root.exists() {
@@ -43,54 +59,128 @@ impl Game {
         fs::write(saves_path, serde_json::to_string_pretty(&new_info)?)?;
         Ok(())
     }
-    pub async fn create_snapshot(&self, describe: &str) -> Result<(), BackupError> {
+    pub async fn create_snapshot(
+        &self,
+        describe: &str,
+        trigger: SnapshotTrigger,
+    ) -> Result<(), BackupError> {
+        let lock = self.lock_snapshots_info().await;
+        self.create_snapshot_locked(describe, trigger)?;
+        drop(lock);
+
+        // 随时同步到云端，失败时记录在日志中稍后重试，不影响本地的备份
+        if get_config()?.settings.cloud_settings.always_sync {
+            sync_later(PendingOperation::UploadGame {
+                game: self.name.clone(),
+            });
+        }
+        // 按本地的保留策略清理旧的存档，失败时不影响已完成的备份
+        if let Err(e) = self.apply_local_retention().await {
+            warn!(target:"rgsm::backup::game","Failed to apply local retention to {}: {:?}", self.name, e);
+        }
+        Result::Ok(())
+    }
+    /// 压缩存档并加入记录，调用者需持有存档记录的锁
+    fn create_snapshot_locked(
+        &self,
+        describe: &str,
+        trigger: SnapshotTrigger,
+    ) -> Result<(), BackupError> {
         let config = get_config()?;
-        let backup_path = path::Path::new(&config.backup_path).join(&self.name); // the backup zip file should be placed here
-        let date = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let layout = RemoteLayout::from_config(&config);
+        let mut snapshot = Snapshot::new(describe, trigger);
         let save_paths = &self.save_paths; // everything you should copy
 
-        let zip_path = backup_path.join([&date, ".zip"].concat());
+        let zip_path = layout.local_snapshot(&self.name, &snapshot.id); // the backup zip file should be placed here
         if let Err(e) = compress_to_file(save_paths, &zip_path) {
             // delete the zip if failed to write
             fs::remove_file(&zip_path)?;
             return Err(BackupError::Compress(e));
         }
 
-        let game_snapshots_info = Snapshot {
-            date,
-            describe: describe.to_string(),
-            path: zip_path
-                .to_str()
-                .ok_or(BackupError::NonePathError)?
-                .to_string(),
-        };
+        snapshot.path = zip_path
+            .to_str()
+            .ok_or(BackupError::NonePathError)?
+            .to_string();
+        // 统计信息只用于展示，读取失败时保持为 0，不影响备份本身
+        if let Err(e) = snapshot.read_archive_stats(&zip_path) {
+            warn!(target:"rgsm::backup::game","Cannot read {:?}: {:?}", zip_path, e);
+        }
         let mut infos = self.get_game_snapshots_info()?;
-        infos.backups.push(game_snapshots_info);
-        self.set_game_snapshots_info(&infos)?;
-
-        // 随时同步到云端
-        if config.settings.cloud_settings.always_sync {
-            let op = config.settings.cloud_settings.backend.get_op()?;
-            // 上传存档记录信息
-            upload_game_snapshots(&op, infos).await?;
-            // 上传对应压缩包
-            // 此处防止路径中出现反斜杠，导致云端无法识别，替换win的反斜杠为斜杠
-            let p = zip_path
+        snapshot.profile.clone_from(&infos.active_profile);
+        snapshot.parent = infos.next_parent();
+        infos.head = Some(snapshot.id.clone());
+        infos.backups.push(snapshot);
+        self.set_game_snapshots_info(&infos)
+    }
+    /// 只保留最新的若干个本地存档，置顶的存档不受影响
+    ///
+    /// 云端有副本的存档只删除本地的压缩包，仅在本地的存档会被彻底删除，
+    /// 开启随时同步时仅在本地的存档还未上传，暂不清理
+    pub async fn apply_local_retention(&self) -> Result<(), BackupError> {
+        let _lock = self.lock_snapshots_info().await;
+        let config = get_config()?;
+        let keep = config.settings.local_retention as usize;
+        let layout = RemoteLayout::from_config(&config);
+        let mut infos = self.get_game_snapshots_info()?;
+        let expired: Vec<String> = expired_snapshots_per_profile(
+            infos
+                .backups
                 .iter()
-                .map(|s| s.to_str().ok_or(BackupError::NonePathError))
-                .collect::<Result<Vec<&str>, BackupError>>()?
-                .join("/");
-            op.write(&p, fs::read(&zip_path)?).await?;
+                .filter(|x| x.storage != SnapshotStorage::Cloud)
+                .map(|x| (x.id.as_str(), x.timestamp, x.pinned, x.profile.as_deref())),
+            keep,
+        )
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        if expired.is_empty() {
+            return Ok(());
         }
-        Result::Ok(())
+        let always_sync = config.settings.cloud_settings.always_sync;
+        let mut removed = Vec::new();
+        for snapshot in infos.backups.iter_mut().filter(|x| expired.contains(&x.id)) {
+            if snapshot.needs_upload() && always_sync {
+                continue;
+            }
+            info!(target:"rgsm::backup::game","Removing local snapshot {} of {} by retention", snapshot.id, self.name);
+            // 压缩包已不存在时视为已删除，其他错误跳过该存档，下次再清理
+            match fs::remove_file(layout.local_snapshot(&self.name, &snapshot.id)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    warn!(target:"rgsm::backup::game","Cannot remove snapshot {} of {}: {:?}", snapshot.id, self.name, e);
+                    continue;
+                }
+                _ => {}
+            }
+            if snapshot.storage == SnapshotStorage::Both {
+                snapshot.storage = SnapshotStorage::Cloud;
+            } else {
+                removed.push(snapshot.id.clone());
+            }
+        }
+        infos.remove(&removed.iter().map(String::as_str).collect::<Vec<_>>());
+        self.set_game_snapshots_info(&infos)?;
+        Ok(())
+    }
+    pub async fn restore_snapshot(
+        &self,
+        id: &str,
+        app_handle: Option<&AppHandle>,
+    ) -> Result<(), BackupError> {
+        self.restore_files(id, app_handle).await?;
+        let _lock = self.lock_snapshots_info().await;
+        self.set_head(id)
     }
-    pub fn restore_snapshot(
+    /// 将存档解压到存档位置，不修改存档记录
+    async fn restore_files(
         &self,
-        date: &str,
+        id: &str,
         app_handle: Option<&AppHandle>,
     ) -> Result<(), BackupError> {
         let config = get_config()?;
-        let backup_path = path::Path::new(&config.backup_path).join(&self.name);
+        let layout = RemoteLayout::from_config(&config);
+        let backup_path = layout.local_game_dir(&self.name);
+        let storage = self.get_game_snapshots_info()?.find(id).map(|x| x.storage);
         if config.settings.extra_backup_when_apply {
             info!(target:"rgsm::backup::game","Creating extra backup.");
             if let Err(e) = self.create_overwrite_snapshot() {
@@ -98,8 +188,137 @@ impl Game {
                 return Err(BackupError::ExtraBackupFailed);
             }
         }
-        decompress_from_file(&self.save_paths, &backup_path, date, app_handle)?;
-        Result::Ok(())
+        // 仅存在于云端的存档需要先下载，恢复后删除以保持其仅在云端的状态
+        if storage == Some(SnapshotStorage::Cloud) {
+            let zip_path = layout.local_snapshot(&self.name, id);
+            info!(target:"rgsm::backup::game","Fetching cloud-only snapshot {} for {}", id, self.name);
+            let op = config.settings.cloud_settings.backend.get_op()?;
+            fs::write(
+                &zip_path,
+                read_object(&op, &layout.remote_snapshot(&self.name, id)).await?,
+            )?;
+            let result = decompress_from_file(&self.save_paths, &backup_path, id, app_handle);
+            fs::remove_file(&zip_path)?;
+            result?;
+        } else {
+            decompress_from_file(&self.save_paths, &backup_path, id, app_handle)?;
+        }
+        Ok(())
+    }
+    /// 之后创建的存档接在恢复的存档之后，形成新的分支，调用者需持有存档记录的锁
+    ///
+    /// 恢复期间记录可能已被修改，重新读取后再写入
+    fn set_head(&self, id: &str) -> Result<(), BackupError> {
+        let mut infos = self.get_game_snapshots_info()?;
+        if infos.find(id).is_some() {
+            infos.head = Some(id.to_string());
+            self.set_game_snapshots_info(&infos)?;
+        }
+        Ok(())
+    }
+    /// 当前档案中最新的存档
+    pub fn latest_snapshot(&self) -> Result<String, BackupError> {
+        let infos = self.get_game_snapshots_info()?;
+        infos
+            .latest(infos.active_profile.as_deref())
+            .map(|x| x.id.clone())
+            .ok_or(BackupError::NoBackupAvailable)
+    }
+    pub async fn create_profile(&self, profile: &str) -> Result<(), BackupError> {
+        let _lock = self.lock_snapshots_info().await;
+        let mut infos = self.get_game_snapshots_info()?;
+        let profile = profile.trim();
+        if profile.is_empty() || infos.has_profile(Some(profile)) {
+            return Err(BackupError::ProfileExists {
+                name: self.name.clone(),
+                profile: profile.to_string(),
+            });
+        }
+        infos.profiles.push(profile.to_string());
+        self.set_game_snapshots_info(&infos)
+    }
+    /// Switch to another profile, `None` is the default profile
+    ///
+    /// The live save is backed up into the current profile first, then the newest
+    /// snapshot of the target profile is restored. A profile without snapshots
+    /// starts from the live save. When the restore fails, the backup just made
+    /// is restored again and the active profile is left unchanged
+    pub async fn switch_profile(
+        &self,
+        profile: Option<&str>,
+        app_handle: Option<&AppHandle>,
+    ) -> Result<(), BackupError> {
+        // 整个切换过程中持有锁，避免其他操作在备份和恢复之间修改记录
+        let lock = self.lock_snapshots_info().await;
+        let infos = self.get_game_snapshots_info()?;
+        if !infos.has_profile(profile) {
+            return Err(BackupError::ProfileNotExist {
+                name: self.name.clone(),
+                profile: profile.unwrap_or_default().to_string(),
+            });
+        }
+        if infos.active_profile.as_deref() == profile {
+            return Ok(());
+        }
+        let target = infos.latest(profile).map(|x| x.id.clone());
+
+        self.create_snapshot_locked("Switch profile", SnapshotTrigger::ProfileSwitch)?;
+        if let Some(target) = target {
+            let current = self.get_game_snapshots_info()?.head;
+            if let Err(e) = self.restore_files(&target, app_handle).await {
+                error!(target:"rgsm::backup::game","Failed to restore {} of {}, rolling back: {:?}", target, self.name, e);
+                if let Some(current) = current {
+                    if let Err(e) = self.restore_files(&current, app_handle).await {
+                        error!(target:"rgsm::backup::game","Failed to roll back {}: {:?}", self.name, e);
+                    }
+                }
+                return Err(e);
+            }
+            self.set_head(&target)?;
+        }
+
+        let config = get_config()?;
+        let mut infos = self.get_game_snapshots_info()?;
+        infos.active_profile = profile.map(str::to_string);
+        self.set_game_snapshots_info(&infos)?;
+        drop(lock);
+        info!(target:"rgsm::backup::game","Switched {} to profile {:?}", self.name, profile);
+        if config.settings.cloud_settings.always_sync {
+            sync_later(PendingOperation::UploadGame {
+                game: self.name.clone(),
+            });
+        }
+        if let Err(e) = self.apply_local_retention().await {
+            warn!(target:"rgsm::backup::game","Failed to apply local retention to {}: {:?}", self.name, e);
+        }
+        Ok(())
+    }
+    /// 删除本地的存档压缩包，只保留云端的副本
+    pub async fn evict_snapshot(&self, id: &str) -> Result<(), BackupError> {
+        let _lock = self.lock_snapshots_info().await;
+        let config = get_config()?;
+        let layout = RemoteLayout::from_config(&config);
+        let mut infos = self.get_game_snapshots_info()?;
+        let snapshot = infos.find_mut(id).ok_or(BackupError::BackupNotExist {
+            name: self.name.clone(),
+            id: id.to_string(),
+        })?;
+        let not_in_cloud = BackupError::NotInCloud {
+            name: self.name.clone(),
+            id: id.to_string(),
+        };
+        if snapshot.storage != SnapshotStorage::Both {
+            return Err(not_in_cloud);
+        }
+        // 再次确认云端文件存在，避免删除唯一的副本
+        let op = config.settings.cloud_settings.backend.get_op()?;
+        if !op.is_exist(&layout.remote_snapshot(&self.name, id)).await? {
+            return Err(not_in_cloud);
+        }
+        fs::remove_file(layout.local_snapshot(&self.name, id))?;
+        snapshot.storage = SnapshotStorage::Cloud;
+        self.set_game_snapshots_info(&infos)?;
+        Ok(())
     }
     pub fn create_overwrite_snapshot(&self) -> Result<(), BackupError> {
         let config = get_config()?;
@@ -137,75 +356,238 @@ impl Game {
         }
         Result::Ok(())
     }
-    pub async fn delete_snapshot(&self, date: &str) -> Result<(), BackupError> {
+    pub async fn delete_snapshot(&self, id: &str) -> Result<(), BackupError> {
         let config = get_config()?;
-        let save_path = PathBuf::from(&config.backup_path)
-            .join(&self.name)
-            .join(date.to_string() + ".zip");
-        fs::remove_file(&save_path)?;
-
+        let layout = RemoteLayout::from_config(&config);
+        let save_path = layout.local_snapshot(&self.name, id);
+        let lock = self.lock_snapshots_info().await;
         let mut saves = self.get_game_snapshots_info()?;
-        saves.backups.retain(|x| x.date != date);
+        let cloud_only = saves
+            .find(id)
+            .is_some_and(|x| x.storage == SnapshotStorage::Cloud);
+        // 仅存在于云端的存档在本地没有压缩包
+        if !cloud_only {
+            fs::remove_file(&save_path)?;
+        }
+
+        saves.remove(&[id]);
         self.set_game_snapshots_info(&saves)?;
+        drop(lock);
 
         // 随时同步到云端
-        if config.settings.cloud_settings.always_sync {
-            let op = config.settings.cloud_settings.backend.get_op()?;
-            // 上传存档记录信息
-            upload_game_snapshots(&op, saves).await?;
-            // 删除对应压缩包
-            // 此处防止路径中出现反斜杠，导致云端无法识别，替换win的反斜杠为斜杠
-            let p = save_path
-                .iter()
-                .map(|s| s.to_str().ok_or(BackupError::NonePathError))
-                .collect::<Result<Vec<&str>, BackupError>>()?
-                .join("/");
-            op.delete(&p).await?;
+        // 仅存在于云端的存档也需要从云端删除，否则将无法再访问
+        if cloud_only || config.settings.cloud_settings.always_sync {
+            sync_later(PendingOperation::DeleteSnapshot {
+                game: self.name.clone(),
+                id: id.to_string(),
+            });
         }
         Ok(())
     }
     pub async fn delete_game(&self) -> Result<(), BackupError> {
-        let mut config = get_config()?;
-        let backup_path = PathBuf::from(&config.backup_path).join(&self.name);
+        let config = get_config()?;
+        let layout = RemoteLayout::from_config(&config);
+        let backup_path = layout.local_game_dir(&self.name);
         fs::remove_dir_all(&backup_path)?;
 
-        config.games.retain(|x| x.name != self.name);
-        set_config(&config).await?;
+        update_config(|config| {
+            config.games.retain(|x| x.name != self.name);
+            Ok::<(), BackupError>(())
+        })
+        .await?;
 
-        // 随时同步到云端
+        // 随时同步到云端，删除云端的目录后也会上传新的配置文件
         if config.settings.cloud_settings.always_sync {
-            let op = config.settings.cloud_settings.backend.get_op()?;
-            info!(target:"rgsm::backup::game",
-                "Delete Game: {:#?}",
-                backup_path.to_str().ok_or(BackupError::NonePathError)?
-            );
-            // 此处防止路径中出现反斜杠，导致云端无法识别，替换win的反斜杠为斜杠
-            let p = backup_path
-                .iter()
-                .map(|s| s.to_str().ok_or(BackupError::NonePathError))
-                .collect::<Result<Vec<&str>, BackupError>>()?
-                .join("/");
-            op.remove_all(&p).await?;
-            // 也上传新的配置文件
-            upload_config(&op).await?;
+            info!(target:"rgsm::backup::game", "Delete Game: {:#?}", layout.remote_game_dir(&self.name));
+            sync_later(PendingOperation::DeleteGame {
+                game: self.name.clone(),
+            });
         }
 
         Ok(())
     }
     pub async fn set_snapshot_description(
         &self,
-        date: &str,
+        id: &str,
         describe: &str,
     ) -> Result<(), BackupError> {
+        let _lock = self.lock_snapshots_info().await;
+        let mut saves = self.get_game_snapshots_info()?;
+        let snapshot = saves.find_mut(id).ok_or(BackupError::BackupNotExist {
+            name: self.name.clone(),
+            id: id.to_string(),
+        })?;
+        snapshot.describe = describe.to_string();
+        self.set_game_snapshots_info(&saves)?;
+        Ok(())
+    }
+    pub async fn add_snapshot_tags(&self, id: &str, tags: &[String]) -> Result<(), BackupError> {
+        self.update_snapshot(id, |snapshot| snapshot.add_tags(tags))
+            .await
+    }
+    pub async fn remove_snapshot_tags(&self, id: &str, tags: &[String]) -> Result<(), BackupError> {
+        self.update_snapshot(id, |snapshot| snapshot.remove_tags(tags))
+            .await
+    }
+    async fn update_snapshot<F: FnOnce(&mut Snapshot)>(
+        &self,
+        id: &str,
+        f: F,
+    ) -> Result<(), BackupError> {
+        let _lock = self.lock_snapshots_info().await;
+        let mut saves = self.get_game_snapshots_info()?;
+        let snapshot = saves.find_mut(id).ok_or(BackupError::BackupNotExist {
+            name: self.name.clone(),
+            id: id.to_string(),
+        })?;
+        f(snapshot);
+        self.set_game_snapshots_info(&saves)
+    }
+    pub fn get_branch_snapshots(&self, branch: &str) -> Result<Vec<Snapshot>, BackupError> {
+        let saves = self.get_game_snapshots_info()?;
+        let snapshots = saves
+            .branch_snapshots(branch)
+            .ok_or(BackupError::BranchNotExist {
+                name: self.name.clone(),
+                id: branch.to_string(),
+            })?;
+        Ok(snapshots.into_iter().cloned().collect())
+    }
+    /// 名称为空时删除分支的名称
+    pub async fn set_branch_name(&self, branch: &str, name: &str) -> Result<(), BackupError> {
+        let _lock = self.lock_snapshots_info().await;
         let mut saves = self.get_game_snapshots_info()?;
-        let pos = saves.backups.iter().position(|x| x.date == date).ok_or(
-            BackupError::BackupNotExist {
+        if !saves.set_branch_name(branch, name) {
+            return Err(BackupError::BranchNotExist {
                 name: self.name.clone(),
-                date: date.to_string(),
-            },
-        )?;
-        saves.backups[pos].describe = describe.to_string();
+                id: branch.to_string(),
+            });
+        }
+        self.set_game_snapshots_info(&saves)
+    }
+    pub async fn set_snapshot_pinned(&self, id: &str, pinned: bool) -> Result<(), BackupError> {
+        let config = get_config()?;
+        let lock = self.lock_snapshots_info().await;
+        let mut saves = self.get_game_snapshots_info()?;
+        let snapshot = saves.find_mut(id).ok_or(BackupError::BackupNotExist {
+            name: self.name.clone(),
+            id: id.to_string(),
+        })?;
+        snapshot.pinned = pinned;
+        // 置顶后不再受云端保留策略的限制，重新上传
+        if pinned {
+            snapshot.remote_expired = false;
+        }
         self.set_game_snapshots_info(&saves)?;
+        drop(lock);
+        // 置顶状态需要同步到云端，云端的清理也会参考它
+        if config.settings.cloud_settings.always_sync {
+            sync_later(PendingOperation::UploadGame {
+                game: self.name.clone(),
+            });
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backup::SaveUnitType;
+    use crate::config::{app_paths, init_test_config};
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::task::{Context, Waker};
+
+    /// 执行到第一次等待为止，返回是否仍未完成
+    fn poll_once<F: Future>(future: Pin<&mut F>) -> bool {
+        future
+            .poll(&mut Context::from_waker(Waker::noop()))
+            .is_pending()
+    }
+
+    /// 测试用的游戏，配置的备份路径在测试目录下
+    fn test_game(name: &str) -> Game {
+        init_test_config();
+        let save_dir = app_paths().default_backup_path().with_file_name(name);
+        fs::create_dir_all(&save_dir).unwrap();
+        let save_file = save_dir.join("save.dat");
+        fs::write(&save_file, name).unwrap();
+        let game = Game {
+            name: name.to_string(),
+            save_paths: vec![SaveUnit {
+                unit_type: SaveUnitType::File,
+                path: save_file.to_string_lossy().into_owned(),
+                delete_before_apply: false,
+            }],
+            game_path: None,
+        };
+        game.set_game_snapshots_info(&GameSnapshots::new(name))
+            .unwrap();
+        game
+    }
+
+    #[test]
+    fn concurrent_snapshots() {
+        let game = test_game("Concurrent");
+        // 持有锁时新的存档要等待，不会写入记录
+        let lock = tauri::async_runtime::block_on(game.lock_snapshots_info());
+        let mut waiting = pin!(game.create_snapshot("waiting", SnapshotTrigger::Manual));
+        assert!(poll_once(waiting.as_mut()));
+        assert!(game.get_game_snapshots_info().unwrap().backups.is_empty());
+        drop(lock);
+        tauri::async_runtime::block_on(waiting).unwrap();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let game = game.clone();
+                tauri::async_runtime::spawn(async move {
+                    game.create_snapshot(&i.to_string(), SnapshotTrigger::Manual)
+                        .await
+                })
+            })
+            .collect();
+        tauri::async_runtime::block_on(async {
+            for task in tasks {
+                task.await.unwrap().unwrap();
+            }
+        });
+        // 所有存档都被记录，并依次接在前一个存档之后
+        let info = game.get_game_snapshots_info().unwrap();
+        assert_eq!(info.backups.len(), 9);
+        for pair in info.backups.windows(2) {
+            assert_eq!(pair[1].parent.as_deref(), Some(pair[0].id.as_str()));
+        }
+        assert_eq!(info.head.as_deref(), Some(info.backups[8].id.as_str()));
+    }
+
+    #[test]
+    fn restore_keeps_concurrent_changes() {
+        let game = test_game("Restore");
+        tauri::async_runtime::block_on(async {
+            game.create_snapshot("first", SnapshotTrigger::Manual)
+                .await
+                .unwrap();
+            game.create_snapshot("second", SnapshotTrigger::Manual)
+                .await
+                .unwrap();
+        });
+        let first = game.get_game_snapshots_info().unwrap().backups[0]
+            .id
+            .clone();
+
+        // 恢复期间修改的描述不会被覆盖
+        let lock = tauri::async_runtime::block_on(game.lock_snapshots_info());
+        let mut restoring = pin!(game.restore_snapshot(&first, None));
+        assert!(poll_once(restoring.as_mut()));
+        let mut info = game.get_game_snapshots_info().unwrap();
+        info.backups[1].describe = "renamed".to_string();
+        game.set_game_snapshots_info(&info).unwrap();
+        drop(lock);
+        tauri::async_runtime::block_on(restoring).unwrap();
+
+        let info = game.get_game_snapshots_info().unwrap();
+        assert_eq!(info.head.as_deref(), Some(first.as_str()));
+        assert_eq!(info.backups[1].describe, "renamed");
+    }
+}
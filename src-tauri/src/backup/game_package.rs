@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::cloud_sync::RemoteLayout;
+use crate::config::get_config;
+use crate::default_value;
+use crate::errors::BackupError;
+
+use super::library::{remap_path, resolve_name, PathMapping};
+use super::{create_game_backup, Game, GameSnapshots, SnapshotStorage};
+
+/// 游戏文件的格式版本
+const PACKAGE_VERSION: u32 = 1;
+/// 游戏文件中保存游戏定义的文件
+const MANIFEST: &str = "game.json";
+/// 游戏文件中所含存档的记录
+const SNAPSHOTS_INFO: &str = "Backups.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GamePackage {
+    version: u32,
+    exported_at: String,
+    game: Game,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GameImportOptions {
+    #[serde(default = "default_value::empty_vec")]
+    pub path_mappings: Vec<PathMapping>,
+    /// 与现有游戏重名时改名导入，否则报错
+    #[serde(default = "default_value::default_false")]
+    pub rename_on_conflict: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GameImportReport {
+    /// 导入后的游戏名
+    pub name: String,
+    /// 在本设备上不存在的存档路径
+    pub missing_paths: Vec<String>,
+    pub imported_snapshots: usize,
+}
+
+/// Export one game's definition and the chosen snapshots as a `.rgsm-game` file
+///
+/// Only snapshots with a local zip can be included, cloud-only ones are skipped
+pub fn export_game(name: &str, ids: &[String], target: &Path) -> Result<(), BackupError> {
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+    let game = config
+        .games
+        .iter()
+        .find(|g| g.name == name)
+        .ok_or_else(|| BackupError::GameNotFound(name.to_string()))?;
+
+    let mut info = game.get_game_snapshots_info()?;
+    info.backups.retain(|x| {
+        let selected = ids.contains(&x.id);
+        if selected && x.storage == SnapshotStorage::Cloud {
+            warn!(target:"rgsm::backup::game_package","Snapshot {} of {} is only in the cloud, skipped", x.id, name);
+        }
+        selected && x.storage != SnapshotStorage::Cloud
+    });
+
+    let mut zip = ZipWriter::new(File::create(target)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let package = GamePackage {
+        version: PACKAGE_VERSION,
+        exported_at: chrono::Local::now().to_rfc3339(),
+        game: game.clone(),
+    };
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&package)?)?;
+    zip.start_file(SNAPSHOTS_INFO, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&info)?)?;
+    for snapshot in &info.backups {
+        zip.start_file([&snapshot.id, ".zip"].concat(), options)?;
+        io::copy(
+            &mut File::open(layout.local_snapshot(name, &snapshot.id))?,
+            &mut zip,
+        )?;
+    }
+    zip.finish()?;
+    info!(target:"rgsm::backup::game_package","Exported {} with {} snapshots to {:?}", name, info.backups.len(), target);
+    Ok(())
+}
+
+/// Add the game of a `.rgsm-game` file, the same way as `add_game`
+///
+/// Snapshots in the file become the game's backup folder.
+/// Save paths that do not exist on this device are reported, not rejected,
+/// since a game that has not been played yet has no saves
+pub async fn import_game(
+    source: &Path,
+    options: &GameImportOptions,
+) -> Result<GameImportReport, BackupError> {
+    let mut zip = ZipArchive::new(File::open(source)?)?;
+    let package: GamePackage = {
+        let entry = zip
+            .by_name(MANIFEST)
+            .map_err(|_| BackupError::InvalidArchive(format!("{} not found", MANIFEST)))?;
+        serde_json::from_reader(entry)?
+    };
+    if package.version > PACKAGE_VERSION {
+        return Err(BackupError::InvalidArchive(format!(
+            "unsupported version {}",
+            package.version
+        )));
+    }
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+
+    let mut game = package.game;
+    // 备份路径下已有的目录（如已删除游戏留下的）同样视为重名，不合并到其中
+    let mut taken: HashSet<String> = config.games.iter().map(|g| g.name.clone()).collect();
+    if let Ok(entries) = fs::read_dir(layout.local_root()) {
+        taken.extend(
+            entries
+                .flatten()
+                .map(|x| x.file_name().to_string_lossy().into_owned()),
+        );
+    }
+    let original_name = game.name.clone();
+    game.name = resolve_name(&game.name, &taken, options.rename_on_conflict)
+        .ok_or_else(|| BackupError::GameExists(game.name.clone()))?;
+    for unit in &mut game.save_paths {
+        unit.path = remap_path(&unit.path, &options.path_mappings);
+    }
+    game.game_path = game
+        .game_path
+        .map(|path| remap_path(&path, &options.path_mappings));
+    let missing_paths: Vec<String> = game
+        .save_paths
+        .iter()
+        .filter(|unit| !Path::new(&unit.path).exists())
+        .map(|unit| unit.path.clone())
+        .collect();
+
+    // 先解压到备份路径下的临时目录，游戏加入配置后再移动到位
+    let staging = layout
+        .local_root()
+        .join(format!(".import-{}", ulid::Ulid::new()));
+    let imported_snapshots =
+        match stage_snapshots(&mut zip, &staging, &game.name, &original_name, &layout) {
+            Ok(count) => count,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+
+    // 移动完成前不允许上传等操作读取该游戏的存档记录
+    let _lock = game.lock_snapshots_info().await;
+    if let Err(e) = create_game_backup(&game).await {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    // `create_game_backup` 已建立空的备份目录和记录，用暂存的文件替换
+    let game_dir = layout.local_game_dir(&game.name);
+    for entry in fs::read_dir(staging.join(&game.name))? {
+        let entry = entry?;
+        fs::rename(entry.path(), game_dir.join(entry.file_name()))?;
+    }
+    fs::remove_dir_all(&staging)?;
+    info!(target:"rgsm::backup::game_package","Imported {} from {:?} as {}", original_name, source, game.name);
+    Ok(GameImportReport {
+        name: game.name,
+        missing_paths,
+        imported_snapshots,
+    })
+}
+
+/// 将游戏文件中的存档和记录解压到 `staging` 下以 `name` 命名的目录，返回导入的存档数
+fn stage_snapshots<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    staging: &Path,
+    name: &str,
+    original_name: &str,
+    layout: &RemoteLayout,
+) -> Result<usize, BackupError> {
+    let staged = RemoteLayout::new(staging);
+    fs::create_dir_all(staged.local_game_dir(name))?;
+    let mut packaged = match zip.by_name(SNAPSHOTS_INFO) {
+        Ok(mut entry) => {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            GameSnapshots::from_slice(&data)?
+        }
+        Err(_) => GameSnapshots::new(original_name),
+    };
+    packaged.name = name.to_string();
+    layout.localize(&mut packaged);
+    let mut info = GameSnapshots::new(name);
+    for mut snapshot in packaged.backups {
+        // id 会作为文件名，不允许包含路径
+        if snapshot.id.contains(['/', '\\']) || snapshot.id.contains("..") {
+            warn!(target:"rgsm::backup::game_package","Invalid snapshot id {:?}, skipped", snapshot.id);
+            continue;
+        }
+        if info.find(&snapshot.id).is_some() {
+            continue;
+        }
+        let mut entry = zip.by_name(&[&snapshot.id, ".zip"].concat())?;
+        io::copy(
+            &mut entry,
+            &mut File::create(staged.local_snapshot(name, &snapshot.id))?,
+        )?;
+        snapshot.storage = SnapshotStorage::Local;
+        info.backups.push(snapshot);
+    }
+    info.sort();
+    info.check_writable()?;
+    fs::write(
+        staged.local_snapshots_info(name),
+        serde_json::to_string_pretty(&info)?,
+    )?;
+    Ok(info.backups.len())
+}
@@ -1,14 +1,265 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
 
+use super::snapshot::parse_legacy_date;
 use super::Snapshot;
+use crate::default_value;
+use crate::errors::BackupError;
 
+/// 当前 Backups.json 的格式版本
+pub const SNAPSHOTS_INFO_VERSION: u32 = 3;
 
+/// 第 `i` 步将 Backups.json 从版本 `i` 升级到 `i + 1`，没有版本号的文件视为版本 0
+const SNAPSHOTS_INFO_MIGRATIONS: [fn(&mut Value); SNAPSHOTS_INFO_VERSION as usize] =
+    [migrate_to_v1, migrate_to_v2, migrate_to_v3];
 
 /// A backup list info is a json file in a backup folder for a game.
 /// It contains the name of the game,
 /// and all backups' path
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameSnapshots {
+    #[serde(default = "default_value::default")]
+    pub version: u32,
     pub name: String,
     pub backups: Vec<Snapshot>,
-}
\ No newline at end of file
+    /// 当前的存档所基于的存档，新存档会以它作为父存档
+    #[serde(default = "default_value::default_none")]
+    pub head: Option<String>,
+    /// 分支的名称，以分支中第一个存档的 id 作为键
+    #[serde(default = "default_value::default")]
+    pub branch_names: BTreeMap<String, String>,
+    /// 默认档案以外的存档档案，每个档案有各自的存档记录
+    #[serde(default = "default_value::empty_vec")]
+    pub profiles: Vec<String>,
+    /// 当前使用的档案，为空时是默认档案
+    #[serde(default = "default_value::default_none")]
+    pub active_profile: Option<String>,
+}
+
+impl GameSnapshots {
+    pub fn new(name: &str) -> Self {
+        GameSnapshots {
+            version: SNAPSHOTS_INFO_VERSION,
+            name: name.to_string(),
+            backups: Vec::new(),
+            head: None,
+            branch_names: BTreeMap::new(),
+            profiles: Vec::new(),
+            active_profile: None,
+        }
+    }
+
+    /// Parse a Backups.json, upgrading files written by older versions
+    pub fn from_slice(data: &[u8]) -> Result<Self, serde_json::Error> {
+        let mut info: Value = serde_json::from_slice(data)?;
+        migrate_snapshots_info(&mut info);
+        serde_json::from_value(info)
+    }
+
+    /// 更新版本写入的文件可能包含无法识别的字段，覆盖会丢失这些数据
+    pub fn check_writable(&self) -> Result<(), BackupError> {
+        if self.version > SNAPSHOTS_INFO_VERSION {
+            return Err(BackupError::NewerSnapshotsInfo {
+                name: self.name.clone(),
+                version: self.version,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, id: &str) -> Option<&Snapshot> {
+        self.backups.iter().find(|x| x.id == id)
+    }
+
+    pub fn find_mut(&mut self, id: &str) -> Option<&mut Snapshot> {
+        self.backups.iter_mut().find(|x| x.id == id)
+    }
+
+    /// 档案中最新的存档
+    pub fn latest(&self, profile: Option<&str>) -> Option<&Snapshot> {
+        self.backups
+            .iter()
+            .filter(|x| x.profile.as_deref() == profile)
+            .max_by_key(|x| (x.timestamp, &x.id))
+    }
+
+    pub fn has_profile(&self, profile: Option<&str>) -> bool {
+        profile.is_none_or(|profile| self.profiles.iter().any(|x| x == profile))
+    }
+
+    /// 新存档的父存档：当前档案中最后恢复或创建的存档，没有记录时为档案中最新的存档
+    pub fn next_parent(&self) -> Option<String> {
+        let profile = self.active_profile.as_deref();
+        self.head
+            .as_deref()
+            .and_then(|id| self.find(id))
+            .filter(|x| x.profile.as_deref() == profile)
+            .or_else(|| self.latest(profile))
+            .map(|x| x.id.clone())
+    }
+
+    /// Remove snapshots from the list, keeping the history connected
+    ///
+    /// Children of a removed snapshot are attached to its parent, and the
+    /// name of a branch that starts at it moves to its oldest child
+    pub fn remove(&mut self, ids: &[&str]) {
+        for id in ids {
+            let Some(index) = self.backups.iter().position(|x| x.id == *id) else {
+                continue;
+            };
+            let removed = self.backups.remove(index);
+            let mut children: Vec<_> = self
+                .backups
+                .iter_mut()
+                .filter(|x| x.parent.as_deref() == Some(id))
+                .collect();
+            children.sort_by_key(|x| (x.timestamp, x.id.clone()));
+            if let Some(name) = self.branch_names.remove(*id) {
+                if let Some(first) = children.first() {
+                    self.branch_names.entry(first.id.clone()).or_insert(name);
+                }
+            }
+            for child in children {
+                child.parent.clone_from(&removed.parent);
+            }
+            if self.head.as_deref() == Some(id) {
+                self.head = removed.parent;
+            }
+        }
+    }
+
+    /// 按创建时间排序，最新的在最后
+    pub fn sort(&mut self) {
+        self.backups
+            .sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    }
+}
+
+/// 依次执行比文件版本新的迁移，更新版本写入的文件保持原样
+fn migrate_snapshots_info(info: &mut Value) {
+    let version = info.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    if version > SNAPSHOTS_INFO_MIGRATIONS.len() {
+        warn!(target:"rgsm::backup","Backups.json version {} is newer than the software", version);
+        return;
+    }
+    for (step, migrate) in SNAPSHOTS_INFO_MIGRATIONS.iter().enumerate().skip(version) {
+        migrate(info);
+        info["version"] = (step + 1).into();
+    }
+}
+
+/// 版本 0 的存档都只保存在本地，也没有置顶，将其写明
+fn migrate_to_v1(info: &mut Value) {
+    let Some(backups) = info.get_mut("backups").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for snapshot in backups.iter_mut().filter_map(Value::as_object_mut) {
+        snapshot.entry("storage").or_insert_with(|| "Local".into());
+        snapshot.entry("pinned").or_insert_with(|| false.into());
+    }
+}
+
+/// 版本 1 以日期作为存档的标识和文件名，将其作为 id 保留，这样已有的压缩包和云端文件都不需要改名
+fn migrate_to_v2(info: &mut Value) {
+    let Some(backups) = info.get_mut("backups").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for snapshot in backups.iter_mut().filter_map(Value::as_object_mut) {
+        let date = snapshot
+            .get("date")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let time = parse_legacy_date(&date).map(|t| t.fixed_offset());
+        snapshot.entry("id").or_insert_with(|| date.into());
+        snapshot
+            .entry("timestamp")
+            .or_insert_with(|| time.map_or(0, |t| t.timestamp_millis()).into());
+        snapshot.entry("timezone").or_insert_with(|| {
+            time.map_or("+00:00".to_string(), |t| t.offset().to_string())
+                .into()
+        });
+    }
+}
+
+/// 版本 2 及以前的存档没有记录父存档，视为依次创建的一条线
+fn migrate_to_v3(info: &mut Value) {
+    let Some(backups) = info.get_mut("backups").and_then(Value::as_array_mut) else {
+        return;
+    };
+    let mut previous: Option<Value> = None;
+    for snapshot in backups.iter_mut().filter_map(Value::as_object_mut) {
+        let parent = previous.take().unwrap_or(Value::Null);
+        snapshot.entry("parent").or_insert(parent);
+        previous = snapshot.get("id").cloned();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backup::SnapshotTrigger;
+    use serde_json::json;
+
+    #[test]
+    fn migrate_to_v1_adds_storage() {
+        let mut info = json!({"name": "Game", "backups": [
+            {"date": "2024-01-01_00-00-00", "describe": "", "path": "a.zip"},
+            {"date": "2024-01-02_00-00-00", "describe": "", "path": "b.zip", "storage": "Both"}
+        ]});
+        migrate_snapshots_info(&mut info);
+        assert_eq!(info["version"], SNAPSHOTS_INFO_VERSION);
+        assert_eq!(info["backups"][0]["storage"], "Local");
+        assert_eq!(info["backups"][0]["pinned"], false);
+        // 已有的字段保持不变
+        assert_eq!(info["backups"][1]["storage"], "Both");
+
+        // 更新版本写入的文件不会被修改
+        let mut newer = json!({"version": 99, "name": "Game", "backups": []});
+        migrate_snapshots_info(&mut newer);
+        assert_eq!(newer["version"], 99);
+    }
+
+    #[test]
+    fn newer_file_is_read_only() {
+        let newer = br#"{"version": 99, "name": "Game", "backups": [], "unknown": true}"#;
+        let info = GameSnapshots::from_slice(newer).unwrap();
+        assert!(matches!(
+            info.check_writable(),
+            Err(BackupError::NewerSnapshotsInfo { version: 99, .. })
+        ));
+        assert!(GameSnapshots::new("Game").check_writable().is_ok());
+    }
+
+    #[test]
+    fn migrate_to_v2_keeps_dates_as_ids() {
+        let mut info = json!({"version": 1, "name": "Game", "backups": [
+            {"date": "2024-01-01_00-00-00", "describe": "", "path": "a.zip", "storage": "Local", "pinned": false},
+            {"date": "2024-01-02_00-00-00", "describe": "", "path": "b.zip", "storage": "Local", "pinned": false}
+        ]});
+        migrate_snapshots_info(&mut info);
+        let mut info: GameSnapshots = serde_json::from_value(info).unwrap();
+        assert_eq!(info.version, SNAPSHOTS_INFO_VERSION);
+        let old = info.find("2024-01-01_00-00-00").unwrap();
+        assert_eq!(old.date, "2024-01-01_00-00-00");
+        assert_eq!(
+            Some(old.timestamp),
+            parse_legacy_date(&old.date).map(|t| t.timestamp_millis())
+        );
+        assert_eq!(
+            info.backups[1].parent.as_deref(),
+            Some("2024-01-01_00-00-00")
+        );
+
+        // 新存档的 id 不再依赖日期，同一秒内创建的存档也不会冲突
+        let new = Snapshot::new("new", SnapshotTrigger::Manual);
+        let same_second = Snapshot::new("same second", SnapshotTrigger::Manual);
+        assert_ne!(new.id, same_second.id);
+        info.backups.insert(0, new);
+        info.sort();
+        assert_eq!(info.backups[2].describe, "new");
+    }
+}
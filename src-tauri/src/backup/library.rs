@@ -0,0 +1,383 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::cloud_sync::{sync_later, PendingOperation, RemoteLayout};
+use crate::config::{get_config, parse_config, update_config, SharedSettings};
+use crate::default_value;
+use crate::errors::BackupError;
+use crate::traits::Sanitizable;
+
+use super::GameSnapshots;
+
+/// 导出文件的格式版本
+const LIBRARY_VERSION: u32 = 1;
+/// 导出文件中保存配置的文件
+const MANIFEST: &str = "library.json";
+/// 导出文件中存放各游戏备份目录的位置，布局与本地的备份路径一致
+const SAVE_DATA: &str = "save_data";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LibraryManifest {
+    version: u32,
+    exported_at: String,
+    /// 为 false 时配置中的凭据已被替换为占位符
+    includes_credentials: bool,
+    /// 保持原始的 JSON，导入时按配置版本迁移
+    config: serde_json::Value,
+}
+
+/// Replace the `from` prefix of a path with `to` when importing
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PathMapping {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ImportOptions {
+    /// 用于存档和游戏路径，只使用第一个匹配的映射
+    #[serde(default = "default_value::empty_vec")]
+    pub path_mappings: Vec<PathMapping>,
+    /// 与现有游戏重名时改名导入，否则跳过该游戏
+    #[serde(default = "default_value::default_false")]
+    pub rename_on_conflict: bool,
+    /// 同时使用导出文件中的通用设置，即云同步时共享的部分
+    #[serde(default = "default_value::default_false")]
+    pub import_settings: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportedGame {
+    pub original_name: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ImportReport {
+    pub imported: Vec<ImportedGame>,
+    /// 因重名而跳过的游戏
+    pub skipped: Vec<String>,
+}
+
+/// Export the config and the backup folder of every game into a single zip file
+///
+/// Snapshots, `Backups.json` and extra backups are stored as they are on disk.
+/// Credentials are replaced by placeholders unless `include_credentials` is set
+pub fn export_library(target: &Path, include_credentials: bool) -> Result<(), BackupError> {
+    let mut config = get_config()?;
+    if include_credentials {
+        config.settings.cloud_settings.resolve_secrets()?;
+    } else {
+        config = config.sanitize();
+    }
+    let layout = RemoteLayout::from_config(&config);
+
+    let mut zip = ZipWriter::new(File::create(target)?);
+    // 存档本身已经是压缩包，不再压缩
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    let manifest = LibraryManifest {
+        version: LIBRARY_VERSION,
+        exported_at: chrono::Local::now().to_rfc3339(),
+        includes_credentials: include_credentials,
+        config: serde_json::to_value(&config)?,
+    };
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+
+    for game in &config.games {
+        let game_dir = layout.local_game_dir(&game.name);
+        if !game_dir.exists() {
+            warn!(target:"rgsm::backup::library","Backup folder of {} not exists, skipped", game.name);
+            continue;
+        }
+        add_folder(
+            &mut zip,
+            &game_dir,
+            &[SAVE_DATA, "/", &game.name].concat(),
+            options,
+        )?;
+    }
+    zip.finish()?;
+    info!(target:"rgsm::backup::library","Exported {} games to {:?}", config.games.len(), target);
+    Ok(())
+}
+
+/// 将 `folder` 下的所有文件写入 `prefix` 目录
+fn add_folder<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    folder: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+) -> Result<(), BackupError> {
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let path = [
+            prefix,
+            "/",
+            file_name.to_str().ok_or(BackupError::NonePathError)?,
+        ]
+        .concat();
+        if entry.file_type()?.is_dir() {
+            add_folder(zip, &entry.path(), &path, options)?;
+        } else {
+            zip.start_file(path, options)?;
+            io::copy(&mut File::open(entry.path())?, zip)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merge an exported library into the current setup
+///
+/// Games are added next to the existing ones, their backup folders are copied
+/// into this device's backup path. Favorites are not imported
+pub async fn import_library(
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<ImportReport, BackupError> {
+    let mut zip = ZipArchive::new(File::open(source)?)?;
+    let manifest: LibraryManifest = {
+        let entry = zip
+            .by_name(MANIFEST)
+            .map_err(|_| BackupError::InvalidArchive(format!("{} not found", MANIFEST)))?;
+        serde_json::from_reader(entry)?
+    };
+    if manifest.version > LIBRARY_VERSION {
+        return Err(BackupError::InvalidArchive(format!(
+            "unsupported version {}",
+            manifest.version
+        )));
+    }
+    let imported = parse_config(manifest.config)?;
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+
+    // 先确定每个游戏导入后的名称
+    // 备份路径下已有的目录（如已删除游戏留下的）同样视为重名，不合并到其中
+    let mut taken: HashSet<String> = config.games.iter().map(|g| g.name.clone()).collect();
+    if let Ok(entries) = fs::read_dir(layout.local_root()) {
+        taken.extend(
+            entries
+                .flatten()
+                .map(|x| x.file_name().to_string_lossy().into_owned()),
+        );
+    }
+    let mut report = ImportReport::default();
+    let mut games = Vec::new();
+    for mut game in imported.games {
+        let Some(name) = resolve_name(&game.name, &taken, options.rename_on_conflict) else {
+            report.skipped.push(game.name);
+            continue;
+        };
+        taken.insert(name.clone());
+        for unit in &mut game.save_paths {
+            unit.path = remap_path(&unit.path, &options.path_mappings);
+        }
+        game.game_path = game
+            .game_path
+            .map(|path| remap_path(&path, &options.path_mappings));
+        report.imported.push(ImportedGame {
+            original_name: game.name.clone(),
+            name: name.clone(),
+        });
+        game.name = name;
+        games.push(game);
+    }
+
+    // 先解压到备份路径下的临时目录，配置更新成功后再移动到位
+    let staging = layout
+        .local_root()
+        .join(format!(".import-{}", ulid::Ulid::new()));
+    if let Err(e) = stage_games(&mut zip, &staging, &report.imported, &layout) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let shared = SharedSettings::from(&imported.settings);
+    let result = update_config(|config| {
+        // 暂存期间可能有同名的游戏被添加
+        if let Some(game) = games
+            .iter()
+            .find(|g| config.games.iter().any(|x| x.name == g.name))
+        {
+            return Err(BackupError::GameExists(game.name.clone()));
+        }
+        config.games.extend(games);
+        // 只导入各设备通用的设置，备份路径和云同步等保持本机的设置
+        if options.import_settings {
+            config.settings.apply_shared(shared);
+        }
+        Ok(config.settings.cloud_settings.always_sync)
+    })
+    .await;
+    let always_sync = match result {
+        Ok(always_sync) => always_sync,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+    for game in &report.imported {
+        fs::rename(staging.join(&game.name), layout.local_game_dir(&game.name))?;
+    }
+    // 所有游戏都已移走，只剩空目录；没有导入游戏时不存在
+    match fs::remove_dir_all(&staging) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            warn!(target:"rgsm::backup::library","Failed to remove {:?}: {:?}", staging, e);
+        }
+        _ => {}
+    }
+    if always_sync {
+        for game in &report.imported {
+            sync_later(PendingOperation::UploadGame {
+                game: game.name.clone(),
+            });
+        }
+    }
+    info!(target:"rgsm::backup::library","Imported library from {:?}: {:?}", source, report);
+    Ok(report)
+}
+
+/// 将导入的游戏的备份目录解压到 `staging` 下，每个游戏一个以新名称命名的目录
+fn stage_games<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    staging: &Path,
+    imported: &[ImportedGame],
+    layout: &RemoteLayout,
+) -> Result<(), BackupError> {
+    let staged = RemoteLayout::new(staging);
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        // 忽略可能写到备份路径之外的条目
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        let Ok(rest) = path.strip_prefix(SAVE_DATA) else {
+            continue;
+        };
+        let mut components = rest.components();
+        let Some(Component::Normal(original_name)) = components.next() else {
+            continue;
+        };
+        let Some(game) = imported
+            .iter()
+            .find(|g| original_name == g.original_name.as_str())
+        else {
+            continue;
+        };
+        let target = staged.local_game_dir(&game.name).join(components.as_path());
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if target == staged.local_snapshots_info(&game.name) {
+            // 改名后存档记录中的名称和路径也需要更新
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let mut info = GameSnapshots::from_slice(&data)?;
+            info.check_writable()?;
+            info.name.clone_from(&game.name);
+            layout.localize(&mut info);
+            fs::write(&target, serde_json::to_string_pretty(&info)?)?;
+        } else {
+            io::copy(&mut entry, &mut File::create(&target)?)?;
+        }
+    }
+    // 导出时没有备份目录的游戏
+    for game in imported {
+        let info_path = staged.local_snapshots_info(&game.name);
+        if !info_path.exists() {
+            fs::create_dir_all(staged.local_game_dir(&game.name))?;
+            fs::write(
+                &info_path,
+                serde_json::to_string_pretty(&GameSnapshots::new(&game.name))?,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// 与已有游戏重名时，改名为 `名称 (2)` 这样的形式，不允许改名时返回 `None`
+pub(super) fn resolve_name(name: &str, taken: &HashSet<String>, rename: bool) -> Option<String> {
+    if !taken.contains(name) {
+        return Some(name.to_string());
+    }
+    if !rename {
+        return None;
+    }
+    (2..)
+        .map(|i| format!("{} ({})", name, i))
+        .find(|candidate| !taken.contains(candidate))
+}
+
+pub(super) fn remap_path(path: &str, mappings: &[PathMapping]) -> String {
+    mappings
+        .iter()
+        .filter(|m| !m.from.is_empty())
+        .find_map(|m| {
+            // 只在路径分隔处匹配，`C:\Users\old` 不应匹配 `C:\Users\older`
+            path.strip_prefix(&m.from)
+                .filter(|rest| {
+                    rest.is_empty()
+                        || rest.starts_with(['/', '\\'])
+                        || m.from.ends_with(['/', '\\'])
+                })
+                .map(|rest| [m.to.as_str(), rest].concat())
+        })
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rename_and_remap() {
+        let taken: HashSet<String> = ["Game".to_string(), "Game (2)".to_string()].into();
+        assert_eq!(
+            resolve_name("Other", &taken, false),
+            Some("Other".to_string())
+        );
+        assert_eq!(resolve_name("Game", &taken, false), None);
+        assert_eq!(
+            resolve_name("Game", &taken, true),
+            Some("Game (3)".to_string())
+        );
+
+        let mappings = vec![
+            PathMapping {
+                from: String::new(),
+                to: "ignored".to_string(),
+            },
+            PathMapping {
+                from: "C:\\Users\\old".to_string(),
+                to: "D:\\Users\\new".to_string(),
+            },
+        ];
+        assert_eq!(
+            remap_path("C:\\Users\\old\\Saved Games\\Game", &mappings),
+            "D:\\Users\\new\\Saved Games\\Game"
+        );
+        assert_eq!(remap_path("C:\\Users\\old", &mappings), "D:\\Users\\new");
+        assert_eq!(
+            remap_path("C:\\Users\\older\\Saved Games", &mappings),
+            "C:\\Users\\older\\Saved Games"
+        );
+        // 没有匹配的映射时保持不变
+        assert_eq!(
+            remap_path("/home/user/.local/share/Game", &mappings),
+            "/home/user/.local/share/Game"
+        );
+    }
+}
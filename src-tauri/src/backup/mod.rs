@@ -1,13 +1,27 @@
 mod archive;
+mod branch;
+mod check;
 mod game;
+mod game_package;
 mod game_snapshots;
+mod library;
+mod reindex;
+mod retention;
 mod save_unit;
+mod search;
 mod snapshot;
 mod utils;
 
 use archive::{compress_to_file, decompress_from_file};
+pub use branch::SnapshotBranch;
+pub use check::{check_repository, RepositoryReport};
 pub use game::Game;
+pub use game_package::{export_game, import_game, GameImportOptions, GameImportReport};
 pub use game_snapshots::GameSnapshots;
+pub use library::{export_library, import_library, ImportOptions, ImportReport};
+pub use reindex::{reindex_all, reindex_game, ReindexReport};
+pub use retention::expired_snapshots_per_profile;
 pub use save_unit::{SaveUnit, SaveUnitType};
-pub use snapshot::Snapshot;
+pub use search::{search_snapshots, SnapshotQuery, SnapshotSearchResult};
+pub use snapshot::{Snapshot, SnapshotStorage, SnapshotTrigger};
 pub use utils::*;
@@ -0,0 +1,254 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::cloud_sync::{sync_later, PendingOperation, RemoteLayout};
+use crate::config::get_config;
+use crate::errors::BackupError;
+
+use super::{Game, GameSnapshots, Snapshot, SnapshotStorage, SnapshotTrigger};
+
+/// 额外备份所在的子目录，文件名为 `Overwrite_<date>.zip`
+const EXTRA_BACKUP_DIR: &str = "extra_backup";
+const EXTRA_BACKUP_PREFIX: &str = "Overwrite_";
+
+/// What `reindex_game` changed in a game's `Backups.json`
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ReindexReport {
+    pub game: String,
+    /// 磁盘上有压缩包但记录中没有的存档，已重新加入记录
+    pub added: Vec<String>,
+    /// 记录中仅在本地但压缩包已不存在的存档
+    pub missing: Vec<String>,
+    /// 本地压缩包已不存在、但云端有副本的存档，已改为仅在云端
+    pub cloud_only: Vec<String>,
+    /// 从额外备份中收回的存档
+    pub adopted: Vec<String>,
+    /// 仅在云端、但本地压缩包又出现的存档，已改为两处都有
+    pub restored: Vec<String>,
+    /// `Backups.json` 损坏，已从头重建
+    pub rebuilt: bool,
+}
+
+impl ReindexReport {
+    fn changed(&self) -> bool {
+        self.rebuilt
+            || !(self.added.is_empty()
+                && self.cloud_only.is_empty()
+                && self.adopted.is_empty()
+                && self.restored.is_empty())
+    }
+}
+
+/// 游戏目录下的所有存档压缩包，以文件名（不含扩展名）作为 id
+pub(super) fn snapshots_on_disk(game_dir: &Path) -> Result<BTreeSet<String>, BackupError> {
+    let mut ids = BTreeSet::new();
+    for entry in fs::read_dir(game_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "zip") {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                ids.insert(stem.to_string());
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// 使存档记录与磁盘上的压缩包一致
+fn reconcile(
+    info: &mut GameSnapshots,
+    on_disk: &BTreeSet<String>,
+    layout: &RemoteLayout,
+    report: &mut ReindexReport,
+) {
+    for snapshot in &mut info.backups {
+        if on_disk.contains(&snapshot.id) {
+            continue;
+        }
+        match snapshot.storage {
+            SnapshotStorage::Both => {
+                snapshot.storage = SnapshotStorage::Cloud;
+                report.cloud_only.push(snapshot.id.clone());
+            }
+            SnapshotStorage::Local => report.missing.push(snapshot.id.clone()),
+            SnapshotStorage::Cloud => {}
+        }
+    }
+    for id in on_disk {
+        if let Some(snapshot) = info.find_mut(id) {
+            // 仅在云端的存档又出现了本地压缩包
+            if snapshot.storage == SnapshotStorage::Cloud {
+                snapshot.storage = SnapshotStorage::Both;
+                report.restored.push(id.clone());
+            }
+            continue;
+        }
+        let mut snapshot = Snapshot::recovered(id, "Recovered (Reindex)");
+        snapshot.path = layout
+            .local_snapshot(&info.name, id)
+            .to_string_lossy()
+            .into_owned();
+        info.backups.push(snapshot);
+        report.added.push(id.clone());
+    }
+    info.sort();
+    // 找回的存档没有父存档的记录，接在时间上的前一个存档之后
+    for i in 1..info.backups.len() {
+        if info.backups[i].parent.is_none() && report.added.contains(&info.backups[i].id) {
+            info.backups[i].parent = Some(info.backups[i - 1].id.clone());
+        }
+    }
+}
+
+/// 将额外备份移动为普通的存档，以其日期作为 id，已存在时跳过
+fn adopt_extra_backups(
+    info: &GameSnapshots,
+    layout: &RemoteLayout,
+) -> Result<Vec<String>, BackupError> {
+    let extra_dir = layout.local_game_dir(&info.name).join(EXTRA_BACKUP_DIR);
+    if !extra_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut adopted = Vec::new();
+    for entry in fs::read_dir(&extra_dir)? {
+        let path = entry?.path();
+        let Some(date) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(EXTRA_BACKUP_PREFIX))
+            .and_then(|name| name.strip_suffix(".zip"))
+        else {
+            continue;
+        };
+        let target = layout.local_snapshot(&info.name, date);
+        if target.exists() || info.find(date).is_some() {
+            continue;
+        }
+        fs::rename(&path, &target)?;
+        adopted.push(date.to_string());
+    }
+    Ok(adopted)
+}
+
+/// Rebuild a game's `Backups.json` from the zips in its backup folder
+///
+/// Descriptions and pinned flags of known snapshots are kept. An unreadable
+/// `Backups.json` is kept as `Backups.json.corrupt` and rebuilt from scratch
+pub async fn reindex_game(game: &Game, adopt_extra: bool) -> Result<ReindexReport, BackupError> {
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+    let game_dir = layout.local_game_dir(&game.name);
+    fs::create_dir_all(&game_dir)?;
+
+    let mut report = ReindexReport {
+        game: game.name.clone(),
+        ..Default::default()
+    };
+    let info_path = layout.local_snapshots_info(&game.name);
+    let lock = game.lock_snapshots_info().await;
+    let mut info = match fs::read(&info_path) {
+        Ok(data) => GameSnapshots::from_slice(&data).or_else(|e| {
+            warn!(target:"rgsm::backup::reindex","Backups.json of {} is corrupted: {:?}", game.name, e);
+            fs::rename(&info_path, info_path.with_extension("json.corrupt"))?;
+            report.rebuilt = true;
+            Ok::<_, BackupError>(GameSnapshots::new(&game.name))
+        })?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => GameSnapshots::new(&game.name),
+        Err(e) => return Err(e.into()),
+    };
+
+    if adopt_extra {
+        report.adopted = adopt_extra_backups(&info, &layout)?;
+    }
+    reconcile(
+        &mut info,
+        &snapshots_on_disk(&game_dir)?,
+        &layout,
+        &mut report,
+    );
+    for snapshot in &mut info.backups {
+        // 收回的额外备份也是新加入的，改用额外备份的描述
+        if report.adopted.contains(&snapshot.id) {
+            "Overwrite backup (Reindex)".clone_into(&mut snapshot.describe);
+            snapshot.trigger = SnapshotTrigger::PreRestore;
+        }
+        // 补充旧版本和找回的存档缺少的大小和文件数
+        if snapshot.size == 0 && snapshot.storage != SnapshotStorage::Cloud {
+            let zip_path = layout.local_snapshot(&game.name, &snapshot.id);
+            if let Err(e) = snapshot.read_archive_stats(&zip_path) {
+                warn!(target:"rgsm::backup::reindex","Cannot read {:?}: {:?}", zip_path, e);
+            }
+        }
+    }
+    report.added.retain(|id| !report.adopted.contains(id));
+    game.set_game_snapshots_info(&info)?;
+    drop(lock);
+    info!(target:"rgsm::backup::reindex","Reindexed {}: {:?}", game.name, report);
+
+    if report.changed() && config.settings.cloud_settings.always_sync {
+        sync_later(PendingOperation::UploadGame {
+            game: game.name.clone(),
+        });
+    }
+    Ok(report)
+}
+
+/// 对配置中的所有游戏执行 `reindex_game`
+pub async fn reindex_all(adopt_extra: bool) -> Result<Vec<ReindexReport>, BackupError> {
+    let config = get_config()?;
+    let mut reports = Vec::new();
+    for game in &config.games {
+        reports.push(reindex_game(game, adopt_extra).await?);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reconcile_with_disk() {
+        let layout = RemoteLayout::new(std::env::temp_dir());
+        let mut info = GameSnapshots::new("Game");
+        info.backups = vec![
+            Snapshot::fixture("2024-01-01_00-00-00", SnapshotStorage::Local),
+            Snapshot::fixture("2024-01-02_00-00-00", SnapshotStorage::Both),
+            Snapshot::fixture("2024-01-03_00-00-00", SnapshotStorage::Local),
+            Snapshot::fixture("2024-01-04_00-00-00", SnapshotStorage::Cloud),
+        ];
+        info.backups.iter_mut().for_each(|x| x.pinned = true);
+        let on_disk = [
+            "2024-01-03_00-00-00",
+            "2024-01-04_00-00-00",
+            "2023-12-31_00-00-00",
+        ]
+        .map(String::from)
+        .into();
+        let mut report = ReindexReport::default();
+        reconcile(&mut info, &on_disk, &layout, &mut report);
+
+        assert_eq!(report.added, vec!["2023-12-31_00-00-00"]);
+        assert_eq!(report.missing, vec!["2024-01-01_00-00-00"]);
+        assert_eq!(report.cloud_only, vec!["2024-01-02_00-00-00"]);
+        assert_eq!(report.restored, vec!["2024-01-04_00-00-00"]);
+        assert!(report.changed());
+        // 新加入的存档按日期排在最前面，已有存档的描述和置顶保持不变
+        let ids: Vec<_> = info.backups.iter().map(|x| x.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "2023-12-31_00-00-00",
+                "2024-01-01_00-00-00",
+                "2024-01-02_00-00-00",
+                "2024-01-03_00-00-00",
+                "2024-01-04_00-00-00"
+            ]
+        );
+        assert!(info.backups[3].pinned);
+        assert_eq!(info.backups[3].describe, "2024-01-03_00-00-00");
+    }
+}
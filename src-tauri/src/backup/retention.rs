@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+/// Select the snapshots that a "keep the newest `keep`" policy removes
+///
+/// `snapshots` are `(id, timestamp, pinned)` tuples in any order. Pinned snapshots are
+/// always kept and do not take up any of the `keep` slots, `keep == 0` keeps everything
+pub fn expired_snapshots<'a, I>(snapshots: I, keep: usize) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, i64, bool)>,
+{
+    if keep == 0 {
+        return Vec::new();
+    }
+    let mut unpinned: Vec<(&str, i64)> = snapshots
+        .into_iter()
+        .filter(|(_, _, pinned)| !pinned)
+        .map(|(id, timestamp, _)| (id, timestamp))
+        .collect();
+    // 新的在前
+    unpinned.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(a.0)));
+    unpinned.into_iter().skip(keep).map(|(id, _)| id).collect()
+}
+
+/// 每个存档档案分别按 `expired_snapshots` 清理，互不占用保留的数量
+///
+/// `snapshots` are `(id, timestamp, pinned, profile)` tuples
+pub fn expired_snapshots_per_profile<'a, I>(snapshots: I, keep: usize) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, i64, bool, Option<&'a str>)>,
+{
+    let mut profiles: BTreeMap<Option<&str>, Vec<(&str, i64, bool)>> = BTreeMap::new();
+    for (id, timestamp, pinned, profile) in snapshots {
+        profiles
+            .entry(profile)
+            .or_default()
+            .push((id, timestamp, pinned));
+    }
+    profiles
+        .into_values()
+        .flat_map(|snapshots| expired_snapshots(snapshots, keep))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keep_newest_and_pinned() {
+        let snapshots = [
+            ("2024-01-03_00-00-00", 3, false),
+            ("2024-01-01_00-00-00", 1, true),
+            ("2024-01-04_00-00-00", 4, false),
+            ("2024-01-02_00-00-00", 2, false),
+        ];
+        // 置顶的存档即使最旧也会保留
+        assert_eq!(expired_snapshots(snapshots, 2), vec!["2024-01-02_00-00-00"]);
+        assert!(expired_snapshots(snapshots, 0).is_empty());
+        assert!(expired_snapshots(snapshots, 10).is_empty());
+    }
+
+    #[test]
+    fn keep_per_profile() {
+        // 其他档案的存档不会挤占默认档案的数量
+        let profiles = [
+            ("2024-01-01_00-00-00", 1, false, None),
+            ("2024-01-02_00-00-00", 2, false, Some("Alice")),
+            ("2024-01-03_00-00-00", 3, false, Some("Alice")),
+        ];
+        assert_eq!(
+            expired_snapshots_per_profile(profiles, 1),
+            vec!["2024-01-02_00-00-00"]
+        );
+    }
+}
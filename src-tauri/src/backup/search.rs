@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::get_config;
+use crate::default_value;
+use crate::errors::BackupError;
+
+use super::{Snapshot, SnapshotTrigger};
+
+/// Conditions of `search_snapshots`, empty fields match everything
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SnapshotQuery {
+    /// 在描述和标签中查找，不区分大小写
+    #[serde(default = "default_value::default_none")]
+    pub text: Option<String>,
+    /// 需要包含所有这些标签
+    #[serde(default = "default_value::empty_vec")]
+    pub tags: Vec<String>,
+    /// 创建时间的范围，Unix 时间戳（毫秒），包含两端
+    #[serde(default = "default_value::default_none")]
+    pub from: Option<i64>,
+    #[serde(default = "default_value::default_none")]
+    pub to: Option<i64>,
+    /// 满足其中任意一种即可
+    #[serde(default = "default_value::empty_vec")]
+    pub triggers: Vec<SnapshotTrigger>,
+    /// 只在这些游戏中查找
+    #[serde(default = "default_value::empty_vec")]
+    pub games: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SnapshotSearchResult {
+    pub game: String,
+    pub snapshot: Snapshot,
+}
+
+impl SnapshotQuery {
+    fn matches(&self, snapshot: &Snapshot) -> bool {
+        let text = self
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_lowercase);
+        text.is_none_or(|text| {
+            snapshot.describe.to_lowercase().contains(&text)
+                || snapshot
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(&text))
+        }) && self.tags.iter().all(|tag| snapshot.tags.contains(tag))
+            && self.from.is_none_or(|from| snapshot.timestamp >= from)
+            && self.to.is_none_or(|to| snapshot.timestamp <= to)
+            && (self.triggers.is_empty() || self.triggers.contains(&snapshot.trigger))
+    }
+}
+
+/// Find snapshots of all games in the config, newest first
+///
+/// Games whose `Backups.json` cannot be read are skipped
+pub fn search_snapshots(query: &SnapshotQuery) -> Result<Vec<SnapshotSearchResult>, BackupError> {
+    let config = get_config()?;
+    let mut results = Vec::new();
+    for game in &config.games {
+        if !query.games.is_empty() && !query.games.contains(&game.name) {
+            continue;
+        }
+        let info = match game.get_game_snapshots_info() {
+            Ok(info) => info,
+            Err(e) => {
+                warn!(target:"rgsm::backup::search","Cannot read snapshots of {}: {:?}", game.name, e);
+                continue;
+            }
+        };
+        results.extend(
+            info.backups
+                .into_iter()
+                .filter(|x| query.matches(x))
+                .map(|snapshot| SnapshotSearchResult {
+                    game: game.name.clone(),
+                    snapshot,
+                }),
+        );
+    }
+    results.sort_by_key(|x| std::cmp::Reverse(x.snapshot.timestamp));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_text_tags_time_and_trigger() {
+        let mut snapshot = Snapshot::new("Before the Final Boss", SnapshotTrigger::Hotkey);
+        snapshot.add_tags(&[" boss ".to_string(), "act3".to_string(), String::new()]);
+        snapshot.add_tags(&["boss".to_string()]);
+        assert_eq!(snapshot.tags, vec!["boss", "act3"]);
+
+        let query = |f: fn(&mut SnapshotQuery)| {
+            let mut query = SnapshotQuery::default();
+            f(&mut query);
+            query.matches(&snapshot)
+        };
+        assert!(query(|_| {}));
+        assert!(query(|q| q.text = Some("final boss".to_string())));
+        assert!(query(|q| q.text = Some("ACT".to_string())));
+        assert!(!query(|q| q.text = Some("act4".to_string())));
+        assert!(query(
+            |q| q.tags = vec!["boss".to_string(), "act3".to_string()]
+        ));
+        assert!(!query(
+            |q| q.tags = vec!["boss".to_string(), "act4".to_string()]
+        ));
+        assert!(query(|q| q.from = Some(0)));
+        assert!(!query(|q| q.to = Some(0)));
+        assert!(query(
+            |q| q.triggers = vec![SnapshotTrigger::Timer, SnapshotTrigger::Hotkey]
+        ));
+        assert!(!query(|q| q.triggers = vec![SnapshotTrigger::Timer]));
+
+        snapshot.remove_tags(&["boss".to_string()]);
+        assert_eq!(snapshot.tags, vec!["act3"]);
+    }
+}
@@ -1,11 +1,249 @@
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
 use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+use zip::ZipArchive;
+
+use crate::config::Config;
+use crate::default_value;
+use crate::errors::BackupError;
+use crate::quick_actions::QuickActionType;
+
+/// 用于显示的日期格式，也是旧版本存档的文件名
+pub const DATE_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// Where the zip file of a snapshot is stored
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum SnapshotStorage {
+    /// 只在本地
+    #[default]
+    Local,
+    /// 只在云端，恢复时按需下载
+    Cloud,
+    /// 本地和云端都有
+    Both,
+}
+
+/// What started a backup
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum SnapshotTrigger {
+    /// 旧版本创建或从压缩包找回的存档
+    #[default]
+    Unknown,
+    Manual,
+    Timer,
+    Tray,
+    Hotkey,
+    /// 恢复存档前的额外备份，重建记录时收回为普通存档
+    PreRestore,
+    /// 通过本软件启动游戏时
+    Launcher,
+    /// 切换存档档案前保存当前的存档
+    ProfileSwitch,
+}
+
+impl From<&QuickActionType> for SnapshotTrigger {
+    fn from(t: &QuickActionType) -> Self {
+        match t {
+            QuickActionType::Timer => SnapshotTrigger::Timer,
+            QuickActionType::Tray => SnapshotTrigger::Tray,
+            QuickActionType::Hotkey => SnapshotTrigger::Hotkey,
+        }
+    }
+}
 
 /// A backup is a zip file that contains
 /// all the file that the save unit has declared.
-/// The date is the unique indicator for a backup
-#[derive(Debug, Serialize, Deserialize)]
+/// The id is the unique indicator for a backup and the name of its zip file
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Snapshot {
+    /// 新存档为 ULID，旧版本的存档沿用其日期
+    pub id: String,
+    /// 创建时的本地时间，仅用于显示
     pub date: String,
+    /// 创建时间，Unix 时间戳（毫秒）
+    pub timestamp: i64,
+    /// 创建时所在时区与 UTC 的偏移，如 `+08:00`
+    pub timezone: String,
     pub describe: String,
-    pub path: String, // like "D:\\SaveManager\save_data\Game1\date.zip"
-}
\ No newline at end of file
+    pub path: String, // like "D:\\SaveManager\save_data\Game1\id.zip"
+    #[serde(default = "default_value::default")]
+    pub storage: SnapshotStorage,
+    /// 置顶的存档不会被保留策略清理
+    #[serde(default = "default_value::default_false")]
+    pub pinned: bool,
+    /// 云端的保留策略已删除该存档，只保留本地副本，不再上传
+    #[serde(default = "default_value::default_false")]
+    pub remote_expired: bool,
+    // 以下为旧版本没有记录的信息，大小和文件数为 0 表示未知
+    /// 压缩包的大小（字节）
+    #[serde(default = "default_value::default_zero")]
+    pub size: u64,
+    /// 压缩前所有文件的大小（字节）
+    #[serde(default = "default_value::default_zero")]
+    pub uncompressed_size: u64,
+    #[serde(default = "default_value::default_zero")]
+    pub file_count: u64,
+    #[serde(default = "default_value::default")]
+    pub trigger: SnapshotTrigger,
+    /// 创建存档的设备名
+    #[serde(default = "default_value::default")]
+    pub device: String,
+    /// 创建存档的软件版本
+    #[serde(default = "default_value::default")]
+    pub app_version: String,
+    #[serde(default = "default_value::empty_vec")]
+    pub tags: Vec<String>,
+    /// 创建时存档所基于的存档，即最后恢复或创建的存档，为空时是分支的起点
+    #[serde(default = "default_value::default_none")]
+    pub parent: Option<String>,
+    /// 所属的存档档案，为空时属于默认档案
+    #[serde(default = "default_value::default_none")]
+    pub profile: Option<String>,
+}
+
+impl Snapshot {
+    /// 以当前时间创建新存档的记录，`path` 和压缩包的信息由调用方填写
+    pub fn new(describe: &str, trigger: SnapshotTrigger) -> Self {
+        let mut snapshot = Self::at(
+            Ulid::new().to_string(),
+            Local::now().fixed_offset(),
+            describe,
+        );
+        snapshot.trigger = trigger;
+        snapshot.device = device_name().to_string();
+        snapshot.app_version = Config::default().version;
+        snapshot
+    }
+
+    /// Entry for a zip found on disk without a record
+    ///
+    /// The time is taken from the id when it is a ULID or an old date,
+    /// otherwise the current time is used
+    pub fn recovered(id: &str, describe: &str) -> Self {
+        let time = Ulid::from_string(id)
+            .ok()
+            .and_then(|ulid| {
+                Local
+                    .timestamp_millis_opt(ulid.timestamp_ms() as i64)
+                    .single()
+            })
+            .or_else(|| parse_legacy_date(id))
+            .unwrap_or_else(Local::now);
+        Self::at(id.to_string(), time.fixed_offset(), describe)
+    }
+
+    /// 测试用的存档，描述与 id 相同
+    #[cfg(test)]
+    pub fn fixture(id: &str, storage: SnapshotStorage) -> Self {
+        Snapshot {
+            storage,
+            ..Self::recovered(id, id)
+        }
+    }
+
+    fn at(id: String, time: DateTime<FixedOffset>, describe: &str) -> Self {
+        Snapshot {
+            id,
+            date: time.format(DATE_FORMAT).to_string(),
+            timestamp: time.timestamp_millis(),
+            timezone: time.offset().to_string(),
+            describe: describe.to_string(),
+            path: String::new(),
+            storage: SnapshotStorage::Local,
+            pinned: false,
+            remote_expired: false,
+            size: 0,
+            uncompressed_size: 0,
+            file_count: 0,
+            trigger: SnapshotTrigger::Unknown,
+            device: String::new(),
+            app_version: String::new(),
+            tags: Vec::new(),
+            parent: None,
+            profile: None,
+        }
+    }
+
+    /// 加入尚未存在的标签，忽略首尾的空白和空标签
+    pub fn add_tags(&mut self, tags: &[String]) {
+        for tag in tags.iter().map(|x| x.trim()) {
+            if !tag.is_empty() && !self.tags.iter().any(|x| x == tag) {
+                self.tags.push(tag.to_string());
+            }
+        }
+    }
+
+    pub fn remove_tags(&mut self, tags: &[String]) {
+        self.tags
+            .retain(|x| !tags.iter().any(|tag| tag.trim() == x));
+    }
+
+    /// 只在本地且没有被云端的保留策略删除，同步时需要上传
+    pub fn needs_upload(&self) -> bool {
+        self.storage == SnapshotStorage::Local && !self.remote_expired
+    }
+
+    /// 从压缩包读取大小和文件数，只读取压缩包的目录
+    pub fn read_archive_stats(&mut self, zip_path: &Path) -> Result<(), BackupError> {
+        self.size = fs::metadata(zip_path)?.len();
+        let mut zip = ZipArchive::new(File::open(zip_path)?)?;
+        let (mut uncompressed_size, mut file_count) = (0, 0);
+        for i in 0..zip.len() {
+            let entry = zip.by_index_raw(i)?;
+            if entry.is_file() {
+                uncompressed_size += entry.size();
+                file_count += 1;
+            }
+        }
+        self.uncompressed_size = uncompressed_size;
+        self.file_count = file_count;
+        Ok(())
+    }
+}
+
+/// 本设备的主机名，无法获取时为空
+fn device_name() -> &'static str {
+    static DEVICE_NAME: OnceLock<String> = OnceLock::new();
+    DEVICE_NAME.get_or_init(|| sysinfo::System::host_name().unwrap_or_default())
+}
+
+/// 旧版本的日期没有时区，按本机的时区解析
+pub(super) fn parse_legacy_date(date: &str) -> Option<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(date, DATE_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    #[test]
+    fn archive_stats_and_trigger() {
+        let zip_path =
+            std::env::temp_dir().join(format!("rgsm_archive_stats_{}.zip", std::process::id()));
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.add_directory("Saves", SimpleFileOptions::default())
+            .unwrap();
+        for (name, len) in [("Saves/slot1.sav", 100), ("config.ini", 20)] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&vec![0u8; len]).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let mut snapshot = Snapshot::new("stats", (&QuickActionType::Hotkey).into());
+        snapshot.read_archive_stats(&zip_path).unwrap();
+        fs::remove_file(&zip_path).unwrap();
+        // 目录不计入文件数
+        assert_eq!(snapshot.file_count, 2);
+        assert_eq!(snapshot.uncompressed_size, 120);
+        assert!(snapshot.size > 0);
+        assert_eq!(snapshot.trigger, SnapshotTrigger::Hotkey);
+        assert_eq!(snapshot.app_version, Config::default().version);
+    }
+}
@@ -0,0 +1,69 @@
+use tracing::error;
+
+use crate::backup::check_repository;
+use crate::config::{get_config, Config};
+
+/// Handle the commands that run without opening the window
+///
+/// `check-repository [--fix]` prints the report as JSON and exits with 1 when
+/// unfixed issues are left. Returns `None` when the app should start normally.
+/// Runs before the config is migrated, so the config file is never rewritten and
+/// `--fix` is refused until the app has migrated the config once
+pub fn run(args: &[String]) -> Option<i32> {
+    match args.first().map(String::as_str) {
+        Some("check-repository") => {
+            attach_console();
+            Some(check(args.iter().any(|x| x == "--fix")))
+        }
+        _ => None,
+    }
+}
+
+/// 发布版本使用 windows 子系统，没有控制台，输出到启动程序的终端
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // 从资源管理器启动时没有父控制台，调用失败也不影响执行
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn check(fix: bool) -> i32 {
+    let config = match get_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Cannot load config: {}", e);
+            return 2;
+        }
+    };
+    // 旧版本的配置尚未迁移，更新版本的配置为只读，此时修复可能写入不兼容的记录
+    let version = Config::default().version;
+    if fix && config.version != version {
+        eprintln!(
+            "The config is for version {}, start version {} once before using --fix",
+            config.version, version
+        );
+        return 2;
+    }
+    match tauri::async_runtime::block_on(check_repository(fix)) {
+        Ok(report) => {
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => error!(target:"rgsm::cli","Cannot serialize report: {:?}", e),
+            }
+            i32::from(report.has_problems())
+        }
+        Err(e) => {
+            eprintln!("Failed to check repository: {}", e);
+            2
+        }
+    }
+}
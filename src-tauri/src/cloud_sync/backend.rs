@@ -1,3 +1,4 @@
+use opendal::layers::{RetryLayer, ThrottleLayer};
 use opendal::services;
 use opendal::Operator;
 use serde::{Deserialize, Serialize};
@@ -6,6 +7,8 @@ use crate::config::get_config;
 use crate::errors::BackendError;
 use crate::traits::Sanitizable;
 
+use super::secret_store::{resolve, system_store, SecretStore};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
 pub enum Backend {
@@ -34,9 +37,12 @@ pub enum Backend {
 impl Backend {
     /// 获取 Operator 实例
     pub fn get_op(&self) -> Result<Operator, BackendError> {
-        let root = get_config()?.settings.cloud_settings.root_path;
-        match self {
-            Backend::Disabled => Err(BackendError::Disabled),
+        let cloud_settings = get_config()?.settings.cloud_settings;
+        let root = cloud_settings.root_path.clone();
+        let mut backend = self.clone();
+        backend.resolve_secrets(system_store())?;
+        let op = match &backend {
+            Backend::Disabled => return Err(BackendError::Disabled),
             Backend::WebDAV {
                 endpoint,
                 username,
@@ -47,7 +53,7 @@ impl Backend {
                 builder.username(username);
                 builder.password(password);
                 builder.root(&root);
-                Ok(Operator::new(builder)?.finish())
+                Operator::new(builder)?.layer(retry_layer()).finish()
             }
             Backend::S3 {
                 endpoint,
@@ -63,11 +69,39 @@ impl Backend {
                 builder.access_key_id(access_key_id);
                 builder.secret_access_key(secret_access_key);
                 builder.root(&root);
-                Ok(Operator::new(builder)?.finish())
+                Operator::new(builder)?.layer(retry_layer()).finish()
             }
+        };
+        // ThrottleLayer 只限制写入，下载限速见 `read_object`
+        match cloud_settings.upload_bandwidth() {
+            Some(bandwidth) => Ok(op.layer(ThrottleLayer::new(bandwidth, bandwidth))),
+            None => Ok(op),
         }
     }
 
+    /// 后端中需要保护的凭据，以及它们在安全存储中的名称
+    pub(super) fn secrets_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        match self {
+            Backend::Disabled => vec![],
+            Backend::WebDAV { password, .. } => vec![("webdav.password", password)],
+            Backend::S3 {
+                access_key_id,
+                secret_access_key,
+                ..
+            } => vec![
+                ("s3.access_key_id", access_key_id),
+                ("s3.secret_access_key", secret_access_key),
+            ],
+        }
+    }
+
+    /// 将凭据引用替换为安全存储中的实际值
+    pub fn resolve_secrets(&mut self, store: &dyn SecretStore) -> Result<(), BackendError> {
+        self.secrets_mut()
+            .into_iter()
+            .try_for_each(|(_, value)| resolve(store, value))
+    }
+
     /// 检查后端是否可用
     pub async fn check(&self) -> Result<(), BackendError> {
         self.get_op()?.check().await?;
@@ -75,6 +109,11 @@ impl Backend {
     }
 }
 
+/// 网络不稳定时自动重试，重试间隔按指数退避
+fn retry_layer() -> RetryLayer {
+    RetryLayer::new().with_max_times(5).with_jitter()
+}
+
 impl Sanitizable for Backend {
     fn sanitize(self) -> Self {
         match self {
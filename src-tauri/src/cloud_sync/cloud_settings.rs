@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 
 use crate::default_value;
+use crate::errors::BackendError;
 use crate::traits::Sanitizable;
 
-use super::Backend;
+use super::secret_store::{resolve, stash, system_store, unstash, SecretStorage, SecretStore};
+use super::{Backend, EncryptionSettings};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CloudSettings {
@@ -19,8 +21,31 @@ pub struct CloudSettings {
     /// 云同步后端设置
     #[serde(default = "default_value::default_backend")]
     pub backend: Backend,
+    /// 云端加密设置
+    #[serde(default = "default_value::default")]
+    pub encryption: EncryptionSettings,
+    /// 云端凭据的存放位置
+    #[serde(default = "default_value::default")]
+    pub secret_storage: SecretStorage,
+    /// 上传限速，单位 KiB/s，为0则不限速
+    #[serde(default = "default_value::default_zero")]
+    pub upload_limit: u64,
+    /// 下载限速，单位 KiB/s，为0则不限速
+    #[serde(default = "default_value::default_zero")]
+    pub download_limit: u64,
+    /// 列表中的游戏运行时暂停随时同步，游戏退出后再上传
+    ///
+    /// 目前不检测按流量计费的网络，需要时可配合限速使用
+    #[serde(default = "default_value::default_false")]
+    pub pause_while_playing: bool,
+    /// 云端保留的存档数量，为0则全部保留
+    #[serde(default = "default_value::default_zero")]
+    pub remote_retention: u64,
 }
 
+/// 限速的最小值，避免单次写入超过令牌桶的容量
+const MIN_BANDWIDTH: u64 = 64 * 1024;
+
 impl Default for CloudSettings {
     fn default() -> Self {
         CloudSettings {
@@ -28,6 +53,63 @@ impl Default for CloudSettings {
             auto_sync_interval: 0,
             root_path: "/game-save-manager".to_string(),
             backend: Backend::Disabled,
+            encryption: EncryptionSettings::default(),
+            secret_storage: SecretStorage::default(),
+            upload_limit: 0,
+            download_limit: 0,
+            pause_while_playing: false,
+            remote_retention: 0,
+        }
+    }
+}
+
+impl CloudSettings {
+    /// 上传限速，单位字节每秒，不限速时为 `None`
+    pub fn upload_bandwidth(&self) -> Option<u32> {
+        Self::bandwidth(self.upload_limit)
+    }
+
+    /// 下载限速，单位字节每秒，不限速时为 `None`
+    pub fn download_bandwidth(&self) -> Option<u32> {
+        Self::bandwidth(self.download_limit)
+    }
+
+    fn bandwidth(limit: u64) -> Option<u32> {
+        (limit > 0).then(|| u32::try_from((limit * 1024).max(MIN_BANDWIDTH)).unwrap_or(u32::MAX))
+    }
+
+    fn secrets_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        let mut secrets = self.backend.secrets_mut();
+        secrets.push(("encryption.passphrase", &mut self.encryption.passphrase));
+        secrets
+    }
+
+    /// 将凭据移入安全存储，配置中只保留引用
+    pub fn stash_secrets(&mut self, store: &dyn SecretStore) -> Result<(), BackendError> {
+        self.secrets_mut()
+            .into_iter()
+            .try_for_each(|(key, value)| stash(store, key, value))
+    }
+
+    /// 将凭据从安全存储中取回，明文保存在配置中
+    pub fn unstash_secrets(&mut self, store: &dyn SecretStore) -> Result<(), BackendError> {
+        self.secrets_mut()
+            .into_iter()
+            .try_for_each(|(key, value)| unstash(store, key, value))
+    }
+
+    /// 将引用替换为实际凭据，安全存储中的凭据保持不变
+    pub fn resolve_secrets(&mut self) -> Result<(), BackendError> {
+        self.secrets_mut()
+            .into_iter()
+            .try_for_each(|(_, value)| resolve(system_store(), value))
+    }
+
+    /// 按照 `secret_storage` 的设置处理凭据，应在写入配置文件前调用
+    pub fn apply_secret_storage(&mut self) -> Result<(), BackendError> {
+        match self.secret_storage {
+            SecretStorage::Config => self.unstash_secrets(system_store()),
+            SecretStorage::System => self.stash_secrets(system_store()),
         }
     }
 }
@@ -36,6 +118,7 @@ impl Sanitizable for CloudSettings {
     fn sanitize(self) -> Self {
         CloudSettings {
             backend: self.backend.sanitize(),
+            encryption: self.encryption.sanitize(),
             ..self
         }
     }
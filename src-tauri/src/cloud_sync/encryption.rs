@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use crate::default_value;
+use crate::errors::BackendError;
+use crate::traits::Sanitizable;
+
+use super::secret_store::{resolve, system_store};
+
+/// 加密文件的文件头，用于识别云端文件是否经过加密
+const MAGIC: &[u8] = b"RGSMENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// 加密后文件增加的大小：文件头、盐、nonce 和 16 字节的认证标签
+pub const ENCRYPTION_OVERHEAD: u64 = (MAGIC.len() + SALT_LEN + NONCE_LEN + 16) as u64;
+
+/// 云端加密设置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EncryptionSettings {
+    /// 是否在上传前对文件进行加密
+    #[serde(default = "default_value::default_false")]
+    pub enabled: bool,
+    /// 用于派生密钥的口令，所有设备需保持一致
+    #[serde(default = "default_value::default")]
+    pub passphrase: String,
+}
+
+impl Sanitizable for EncryptionSettings {
+    fn sanitize(self) -> Self {
+        EncryptionSettings {
+            passphrase: "*passphrase*".to_string(),
+            ..self
+        }
+    }
+}
+
+/// Encrypts and decrypts cloud objects with a passphrase-derived key.
+///
+/// Every object is stored as `MAGIC | salt | nonce | ciphertext`,
+/// the key is derived from the passphrase and the salt with Argon2,
+/// and the content is sealed with XChaCha20-Poly1305.
+pub struct Cipher {
+    passphrase: String,
+    /// 本进程加密时使用的盐，复用以避免重复派生密钥
+    salt: [u8; SALT_LEN],
+    keys: Mutex<HashMap<[u8; SALT_LEN], Key>>,
+}
+
+impl Cipher {
+    pub fn new(passphrase: &str) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Cipher {
+            passphrase: passphrase.to_string(),
+            salt,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 派生（或从缓存中取出）对应盐的密钥
+    fn key(&self, salt: &[u8; SALT_LEN]) -> Result<Key, BackendError> {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(key) = keys.get(salt) {
+            return Ok(*key);
+        }
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| BackendError::Encryption(e.to_string()))?;
+        keys.insert(*salt, key);
+        Ok(key)
+    }
+
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>, BackendError> {
+        let key = self.key(&self.salt)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let cipher_text = XChaCha20Poly1305::new(&key)
+            .encrypt(&nonce, plain)
+            .map_err(|e| BackendError::Encryption(e.to_string()))?;
+
+        let mut data = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + cipher_text.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&cipher_text);
+        Ok(data)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, BackendError> {
+        let body = data
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() >= SALT_LEN + NONCE_LEN)
+            .ok_or(BackendError::CorruptedCloudFile)?;
+        let (salt, rest) = body.split_at(SALT_LEN);
+        let (nonce, cipher_text) = rest.split_at(NONCE_LEN);
+        // safe: split_at 保证了长度为 SALT_LEN
+        let key = self.key(salt.try_into().unwrap())?;
+        // 认证失败说明口令错误或数据被篡改
+        XChaCha20Poly1305::new(&key)
+            .decrypt(XNonce::from_slice(nonce), cipher_text)
+            .map_err(|_| BackendError::WrongPassphrase)
+    }
+}
+
+/// 判断云端文件是否经过加密
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// 根据设置获取加密器，未启用加密时返回 `None`
+///
+/// 密钥派生的开销较大，口令不变时会复用同一个加密器
+pub fn get_cipher(settings: &EncryptionSettings) -> Result<Option<Arc<Cipher>>, BackendError> {
+    static CIPHER: Mutex<Option<Arc<Cipher>>> = Mutex::new(None);
+
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let mut passphrase = settings.passphrase.clone();
+    resolve(system_store(), &mut passphrase)?;
+    if passphrase.is_empty() {
+        return Err(BackendError::MissingPassphrase);
+    }
+    let mut cached = CIPHER.lock().unwrap_or_else(PoisonError::into_inner);
+    match cached.as_ref() {
+        Some(cipher) if cipher.passphrase == passphrase => Ok(Some(cipher.clone())),
+        _ => {
+            let cipher = Arc::new(Cipher::new(&passphrase));
+            *cached = Some(cipher.clone());
+            Ok(Some(cipher))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encrypt_then_decrypt() {
+        let data = b"{\"name\":\"test\",\"backups\":[]}";
+        let encrypted = Cipher::new("passphrase").encrypt(data).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(
+            encrypted.len() as u64,
+            data.len() as u64 + ENCRYPTION_OVERHEAD
+        );
+        assert_ne!(&encrypted[MAGIC.len()..], data);
+
+        // 其他设备使用相同口令也能解密
+        let decrypted = Cipher::new("passphrase").decrypt(&encrypted).unwrap();
+        assert_eq!(decrypted, data);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_detected() {
+        let encrypted = Cipher::new("passphrase").encrypt(b"save").unwrap();
+        assert!(matches!(
+            Cipher::new("another").decrypt(&encrypted),
+            Err(BackendError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            Cipher::new("passphrase").decrypt(&encrypted[..20]),
+            Err(BackendError::CorruptedCloudFile)
+        ));
+    }
+}
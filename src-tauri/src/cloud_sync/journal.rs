@@ -0,0 +1,265 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use opendal::Operator;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::config::{app_paths, get_config, write_atomic};
+use crate::errors::BackendError;
+
+use super::layout::RemoteLayout;
+use super::lock::with_remote_lock;
+use super::pause::running_game;
+use super::prune::prune_remote;
+use super::transfer::{TransferDirection, TransferJob};
+use super::utils::{upload_config, upload_game, upload_game_snapshots};
+
+/// 后台重试的间隔
+const REPLAY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A cloud side effect that has not been applied yet
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum PendingOperation {
+    /// 上传游戏的存档记录和尚未上传的存档
+    UploadGame { game: String },
+    /// 删除云端的存档，旧版本的日志中 id 记为 `date`
+    DeleteSnapshot {
+        game: String,
+        #[serde(alias = "date")]
+        id: String,
+    },
+    /// 删除云端的游戏目录
+    DeleteGame { game: String },
+    /// 上传共享配置
+    UploadConfig,
+}
+
+impl PendingOperation {
+    fn game(&self) -> Option<&str> {
+        match self {
+            PendingOperation::UploadGame { game }
+            | PendingOperation::DeleteSnapshot { game, .. }
+            | PendingOperation::DeleteGame { game } => Some(game),
+            PendingOperation::UploadConfig => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub operation: PendingOperation,
+    pub created_at: String,
+    /// 已尝试的次数和最近一次失败的原因
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+/// 保护日志文件的读-改-写
+static JOURNAL: Mutex<()> = Mutex::new(());
+/// 同一时间只进行一次重放
+static REPLAYING: AtomicBool = AtomicBool::new(false);
+
+fn load() -> Vec<JournalEntry> {
+    let path = app_paths().journal_file();
+    if !path.exists() {
+        return Vec::new();
+    }
+    match fs::read(&path).map(|data| serde_json::from_slice(&data)) {
+        Ok(Ok(entries)) => entries,
+        other => {
+            error!(target:"rgsm::cloud::journal","Cannot read sync journal: {:?}", other.err());
+            Vec::new()
+        }
+    }
+}
+
+fn save(entries: &[JournalEntry]) -> Result<(), BackendError> {
+    // 写入一半时崩溃会丢失整个队列，先写临时文件再替换
+    write_atomic(
+        &app_paths().journal_file(),
+        &serde_json::to_vec_pretty(entries)?,
+    )?;
+    Ok(())
+}
+
+fn update<T, F: FnOnce(&mut Vec<JournalEntry>) -> T>(f: F) -> Result<T, BackendError> {
+    let _guard = JOURNAL.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut entries = load();
+    let result = f(&mut entries);
+    save(&entries)?;
+    Ok(result)
+}
+
+/// 获取所有等待同步的操作
+pub fn pending_operations() -> Vec<JournalEntry> {
+    let _guard = JOURNAL.lock().unwrap_or_else(PoisonError::into_inner);
+    load()
+}
+
+/// 将操作加入日志，重复或已被覆盖的操作会被合并
+fn enqueue(operation: PendingOperation) -> Result<(), BackendError> {
+    update(|entries| merge(entries, operation, chrono::Local::now().to_rfc3339()))
+}
+
+fn merge(entries: &mut Vec<JournalEntry>, operation: PendingOperation, created_at: String) {
+    if entries.iter().any(|e| e.operation == operation) {
+        return;
+    }
+    // 删除游戏后，该游戏之前的其他操作都不再需要
+    if let PendingOperation::DeleteGame { game } = &operation {
+        entries.retain(|e| e.operation.game() != Some(game));
+    }
+    let id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+    entries.push(JournalEntry {
+        id,
+        operation,
+        created_at,
+        attempts: 0,
+        last_error: None,
+    });
+}
+
+/// 记录云端操作，并在后台尝试同步
+///
+/// 本地的操作已经完成，不等待网络，同步失败时操作会保留在日志中，之后重试
+pub fn sync_later(operation: PendingOperation) {
+    info!(target:"rgsm::cloud::journal","Queue cloud operation: {:?}", operation);
+    if let Err(e) = enqueue(operation) {
+        error!(target:"rgsm::cloud::journal","Cannot write sync journal: {:?}", e);
+        return;
+    }
+    tauri::async_runtime::spawn(async {
+        if let Err(e) = replay_journal().await {
+            warn!(target:"rgsm::cloud::journal","Cloud is not reachable, will retry later: {:?}", e);
+        }
+    });
+}
+
+/// 按顺序重放日志中的操作，遇到失败时停止，保留剩余的操作
+pub async fn replay_journal() -> Result<(), BackendError> {
+    if pending_operations().is_empty() {
+        return Ok(());
+    }
+    let config = get_config()?;
+    let cloud_settings = &config.settings.cloud_settings;
+    // 游戏运行时暂停同步，等待游戏退出后再重放
+    if cloud_settings.pause_while_playing {
+        if let Some(game) = running_game(&config.games) {
+            info!(target:"rgsm::cloud::journal","{} is running, sync is paused", game);
+            return Ok(());
+        }
+    }
+    if REPLAYING.swap(true, Ordering::AcqRel) {
+        return Ok(());
+    }
+    let result = async {
+        let op = cloud_settings.backend.get_op()?;
+        with_remote_lock(&op, replay_entries(&op)).await
+    }
+    .await;
+    REPLAYING.store(false, Ordering::Release);
+    result
+}
+
+async fn replay_entries(op: &Operator) -> Result<(), BackendError> {
+    while let Some(entry) = pending_operations().into_iter().next() {
+        match apply(op, &entry.operation).await {
+            Ok(_) => {
+                info!(target:"rgsm::cloud::journal","Synced cloud operation: {:?}", entry.operation);
+                update(|entries| entries.retain(|e| e.id != entry.id))?;
+            }
+            Err(e) => {
+                update(|entries| {
+                    if let Some(failed) = entries.iter_mut().find(|e| e.id == entry.id) {
+                        failed.attempts += 1;
+                        failed.last_error = Some(e.to_string());
+                    }
+                })?;
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn apply(op: &Operator, operation: &PendingOperation) -> Result<(), BackendError> {
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+    let local_game = |name: &str| config.games.iter().find(|g| g.name == name);
+    match operation {
+        PendingOperation::UploadGame { game } => {
+            // 游戏已在本地删除时无需上传
+            if let Some(game) = local_game(game) {
+                let job = TransferJob::new(TransferDirection::Upload, None);
+                upload_game(op, game, &job).await?;
+                // 上传新的存档后按云端的保留策略清理
+                prune_remote(op, Some(&game.name)).await?;
+            }
+        }
+        PendingOperation::DeleteSnapshot { game, id } => {
+            op.delete(&layout.remote_snapshot(game, id)).await?;
+            if let Some(game) = local_game(game) {
+                upload_game_snapshots(op, game.get_game_snapshots_info()?).await?;
+            }
+        }
+        PendingOperation::DeleteGame { game } => {
+            op.remove_all(&layout.remote_game_dir(game)).await?;
+            upload_config(op).await?;
+        }
+        PendingOperation::UploadConfig => upload_config(op).await?,
+    }
+    Ok(())
+}
+
+/// 在后台定期重放日志
+pub fn start_replay_worker() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(REPLAY_INTERVAL);
+        if let Err(e) = tauri::async_runtime::block_on(replay_journal()) {
+            warn!(target:"rgsm::cloud::journal","Failed to replay sync journal: {:?}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_queued_operations() {
+        let mut entries = Vec::new();
+        let upload = PendingOperation::UploadGame {
+            game: "Game".to_string(),
+        };
+        merge(&mut entries, upload.clone(), String::new());
+        merge(&mut entries, PendingOperation::UploadConfig, String::new());
+        // 重复的操作只保留一次
+        merge(&mut entries, upload, String::new());
+        assert_eq!(entries.len(), 2);
+
+        // 删除游戏会取代该游戏之前的操作
+        merge(
+            &mut entries,
+            PendingOperation::DeleteGame {
+                game: "Game".to_string(),
+            },
+            String::new(),
+        );
+        let operations: Vec<_> = entries.iter().map(|e| e.operation.clone()).collect();
+        assert_eq!(
+            operations,
+            vec![
+                PendingOperation::UploadConfig,
+                PendingOperation::DeleteGame {
+                    game: "Game".to_string()
+                }
+            ]
+        );
+        assert_eq!(entries[1].id, 3);
+    }
+}
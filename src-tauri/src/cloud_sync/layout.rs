@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use crate::backup::GameSnapshots;
+use crate::config::Config;
+
+/// 云端存放存档的根目录，与本地的备份路径无关
+///
+/// !NOTICE: 这个地方必须硬编码，因为云端目录必须固定
+const REMOTE_ROOT: &str = "save_data";
+/// 每个游戏的存档记录文件名
+const SNAPSHOTS_INFO: &str = "Backups.json";
+
+/// Maps between the local backup folder (`Config.backup_path`) and the remote root
+///
+/// The remote layout is fixed (`save_data/<game>/<id>.zip`) so that devices
+/// with different backup paths can share the same cloud storage, the local
+/// side follows whatever `backup_path` is configured on this device
+pub struct RemoteLayout {
+    local_root: PathBuf,
+}
+
+impl RemoteLayout {
+    pub fn new<P: Into<PathBuf>>(backup_path: P) -> Self {
+        RemoteLayout {
+            local_root: backup_path.into(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.backup_path)
+    }
+
+    /// 本地存放所有游戏的目录，即 `backup_path`
+    pub fn local_root(&self) -> &Path {
+        &self.local_root
+    }
+
+    /// 云端存放所有游戏的目录，以 `/` 结尾
+    pub fn remote_root(&self) -> String {
+        format!("{}/", REMOTE_ROOT)
+    }
+
+    /// 游戏在云端的目录，以 `/` 结尾
+    pub fn remote_game_dir(&self, game: &str) -> String {
+        format!("{}/{}/", REMOTE_ROOT, game)
+    }
+
+    pub fn remote_snapshots_info(&self, game: &str) -> String {
+        format!("{}{}", self.remote_game_dir(game), SNAPSHOTS_INFO)
+    }
+
+    pub fn remote_snapshot(&self, game: &str, id: &str) -> String {
+        format!("{}{}.zip", self.remote_game_dir(game), id)
+    }
+
+    /// 将云端文件路径拆分为游戏名和文件名，不属于任何游戏的路径返回 `None`
+    pub fn parse_remote<'a>(&self, path: &'a str) -> Option<(&'a str, &'a str)> {
+        let rest = path.trim_start_matches('/').strip_prefix(REMOTE_ROOT)?;
+        let (game, file) = rest.strip_prefix('/')?.split_once('/')?;
+        if game.is_empty() || file.is_empty() || file.contains('/') {
+            return None;
+        }
+        Some((game, file))
+    }
+
+    pub fn local_game_dir(&self, game: &str) -> PathBuf {
+        self.local_root.join(game)
+    }
+
+    pub fn local_snapshots_info(&self, game: &str) -> PathBuf {
+        self.local_game_dir(game).join(SNAPSHOTS_INFO)
+    }
+
+    pub fn local_snapshot(&self, game: &str, id: &str) -> PathBuf {
+        self.local_game_dir(game).join([id, ".zip"].concat())
+    }
+
+    /// 将云端下载的存档记录中的路径改为本设备上的路径
+    ///
+    /// 其他设备上传的记录中保存的是那台设备的本地路径
+    pub fn localize(&self, info: &mut GameSnapshots) {
+        for snapshot in &mut info.backups {
+            snapshot.path = path_to_string(&self.local_snapshot(&info.name, &snapshot.id));
+        }
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backup::{Snapshot, SnapshotStorage};
+
+    #[test]
+    fn custom_absolute_backup_path() {
+        let root = std::env::temp_dir().join("rgsm saves");
+        assert!(root.is_absolute());
+        let custom = RemoteLayout::new(&root);
+        let default = RemoteLayout::from_config(&Config::default());
+
+        // 云端路径与本地备份路径无关，且不会包含本地路径的任何部分
+        let remote = custom.remote_snapshot("Game", "2024-01-01_00-00-00");
+        assert_eq!(remote, "save_data/Game/2024-01-01_00-00-00.zip");
+        assert_eq!(
+            remote,
+            default.remote_snapshot("Game", "2024-01-01_00-00-00")
+        );
+        assert_eq!(
+            custom.remote_snapshots_info("Game"),
+            "save_data/Game/Backups.json"
+        );
+
+        // 本地路径位于各自的备份路径下
+        assert_eq!(
+            custom.local_snapshot("Game", "2024-01-01_00-00-00"),
+            root.join("Game").join("2024-01-01_00-00-00.zip")
+        );
+        assert_eq!(
+            custom.local_snapshots_info("Game"),
+            root.join("Game").join("Backups.json")
+        );
+        assert!(default
+            .local_snapshot("Game", "2024-01-01_00-00-00")
+            .starts_with("./save_data"));
+    }
+
+    #[test]
+    fn parse_remote_paths() {
+        let layout = RemoteLayout::new(std::env::temp_dir());
+        let remote = layout.remote_snapshot("Game", "2024-01-01_00-00-00");
+        assert_eq!(
+            layout.parse_remote(&remote),
+            Some(("Game", "2024-01-01_00-00-00.zip"))
+        );
+        assert_eq!(
+            layout.parse_remote("/save_data/Game/Backups.json"),
+            Some(("Game", "Backups.json"))
+        );
+        assert_eq!(layout.parse_remote(&layout.remote_game_dir("Game")), None);
+        assert_eq!(layout.parse_remote("GameSaveManager.sync.json"), None);
+        assert_eq!(layout.parse_remote("save_data_old/Game/x.zip"), None);
+    }
+
+    #[test]
+    fn localize_downloaded_snapshots() {
+        let root = std::env::temp_dir().join("rgsm saves");
+        let mut snapshot = Snapshot::recovered("2024-01-01_00-00-00", "");
+        snapshot.path = "D:\\SaveManager\\save_data\\Game\\2024-01-01_00-00-00.zip".to_string();
+        snapshot.storage = SnapshotStorage::Both;
+        let mut info = GameSnapshots::new("Game");
+        info.backups = vec![snapshot];
+        RemoteLayout::new(&root).localize(&mut info);
+        assert_eq!(
+            PathBuf::from(&info.backups[0].path),
+            root.join("Game").join("2024-01-01_00-00-00.zip")
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::fs;
+use std::future::Future;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::Duration;
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use opendal::{ErrorKind, Operator};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::config::app_paths;
+use crate::errors::BackendError;
+
+/// 云端锁文件的路径
+const LOCK_PATH: &str = "/GameSaveManager.lock";
+/// 锁的有效期，超过该时间未续期的锁视为失效
+const LEASE_SECS: i64 = 60;
+/// 持有锁期间续期的间隔
+const HEARTBEAT: Duration = Duration::from_secs(20);
+
+/// The lease object stored in the remote root while a device is syncing
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteLock {
+    pub device_id: String,
+    /// 每次获取锁时生成，用于确认写入的锁没有被其他设备覆盖
+    pub token: String,
+    /// 以下时间均为 unix 时间戳（秒）
+    pub acquired_at: i64,
+    pub heartbeat_at: i64,
+    pub expires_at: i64,
+}
+
+impl RemoteLock {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// 本进程持有的锁，同一设备上的多个操作共享同一把锁
+struct LocalLease {
+    holders: usize,
+    token: String,
+    /// 用于停止续期线程
+    stop_heartbeat: Option<Sender<()>>,
+}
+
+static LEASE: Mutex<LocalLease> = Mutex::new(LocalLease {
+    holders: 0,
+    token: String::new(),
+    stop_heartbeat: None,
+});
+
+/// 获取和释放云端的锁需要多次读写，同一进程中依次进行，避免写入不同的 token
+static TRANSITION: tauri::async_runtime::Mutex<()> = tauri::async_runtime::Mutex::const_new(());
+
+fn lease() -> std::sync::MutexGuard<'static, LocalLease> {
+    LEASE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 获取本设备的 id，首次调用时生成并保存
+pub fn device_id() -> &'static str {
+    static DEVICE_ID: OnceLock<String> = OnceLock::new();
+    DEVICE_ID.get_or_init(|| {
+        if let Ok(id) = fs::read_to_string(app_paths().device_file()) {
+            if !id.trim().is_empty() {
+                return id.trim().to_string();
+            }
+        }
+        let id = random_hex(8);
+        if let Err(e) = fs::write(app_paths().device_file(), &id) {
+            warn!(target:"rgsm::cloud::lock","Cannot save device id: {:?}", e);
+        }
+        id
+    })
+}
+
+async fn read_lock(op: &Operator) -> Result<Option<RemoteLock>, BackendError> {
+    match op.read(LOCK_PATH).await {
+        Ok(data) => Ok(serde_json::from_slice(&data.to_vec()).ok()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn write_lock(op: &Operator, token: &str, acquired_at: i64) -> Result<(), BackendError> {
+    let now = chrono::Utc::now().timestamp();
+    let lock = RemoteLock {
+        device_id: device_id().to_string(),
+        token: token.to_string(),
+        acquired_at,
+        heartbeat_at: now,
+        expires_at: now + LEASE_SECS,
+    };
+    op.write(LOCK_PATH, serde_json::to_vec_pretty(&lock)?)
+        .await?;
+    Ok(())
+}
+
+fn locked_by(lock: &RemoteLock) -> BackendError {
+    let expires_at = chrono::DateTime::from_timestamp(lock.expires_at, 0)
+        .map(|t| t.with_timezone(&chrono::Local).to_rfc3339())
+        .unwrap_or_default();
+    BackendError::Locked {
+        device: lock.device_id.clone(),
+        expires_at,
+    }
+}
+
+/// 获取云端的锁，其他设备持有未过期的锁时返回错误
+async fn acquire(op: &Operator) -> Result<(), BackendError> {
+    let _transition = TRANSITION.lock().await;
+    {
+        let mut lease = lease();
+        if lease.holders > 0 {
+            lease.holders += 1;
+            return Ok(());
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    if let Some(lock) = read_lock(op).await? {
+        if lock.device_id != device_id() {
+            if !lock.is_expired(now) {
+                return Err(locked_by(&lock));
+            }
+            warn!(target:"rgsm::cloud::lock","Recovering stale lock held by device {}", lock.device_id);
+        }
+    }
+    let token = random_hex(16);
+    write_lock(op, &token, now).await?;
+    // 云端存储没有原子操作，写入后再读取一次，确认没有被其他设备同时获取
+    match read_lock(op).await? {
+        Some(lock) if lock.token == token => {}
+        Some(lock) => return Err(locked_by(&lock)),
+        None => {
+            return Err(BackendError::Unexpected(anyhow::anyhow!(
+                "Remote lock disappeared after writing"
+            )))
+        }
+    }
+    info!(target:"rgsm::cloud::lock","Acquired remote lock as device {}", device_id());
+
+    let mut lease = lease();
+    lease.holders += 1;
+    if lease.stop_heartbeat.is_none() {
+        lease.token = token.clone();
+        lease.stop_heartbeat = Some(start_heartbeat(op.clone(), token, now));
+    }
+    Ok(())
+}
+
+/// 定期续期，直到锁被释放
+fn start_heartbeat(op: Operator, token: String, acquired_at: i64) -> Sender<()> {
+    let (tx, rx) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        // 收到停止信号或发送端被丢弃时退出
+        while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(HEARTBEAT) {
+            let result = tauri::async_runtime::block_on(write_lock(&op, &token, acquired_at));
+            if let Err(e) = result {
+                error!(target:"rgsm::cloud::lock","Cannot renew remote lock: {:?}", e);
+            }
+        }
+    });
+    tx
+}
+
+/// 释放锁，本进程中最后一个持有者释放时才删除云端的锁
+async fn release(op: &Operator) -> Result<(), BackendError> {
+    let _transition = TRANSITION.lock().await;
+    let token = {
+        let mut lease = lease();
+        lease.holders = lease.holders.saturating_sub(1);
+        if lease.holders > 0 {
+            return Ok(());
+        }
+        // 发送失败说明续期线程已经退出，无需处理
+        if let Some(stop) = lease.stop_heartbeat.take() {
+            let _ = stop.send(());
+        }
+        std::mem::take(&mut lease.token)
+    };
+    // 锁已被其他设备接管时不删除
+    if let Some(lock) = read_lock(op).await? {
+        if lock.token == token {
+            op.delete(LOCK_PATH).await?;
+            info!(target:"rgsm::cloud::lock","Released remote lock");
+        }
+    }
+    Ok(())
+}
+
+/// Run `f` while holding the remote lock
+///
+/// The lock is released even if `f` fails
+pub async fn with_remote_lock<T, E, F>(op: &Operator, f: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<BackendError>,
+{
+    acquire(op).await?;
+    let result = f.await;
+    if let Err(e) = release(op).await {
+        error!(target:"rgsm::cloud::lock","Cannot release remote lock: {:?}", e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 各测试共用本进程的锁状态，不能同时运行
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn another_device_holds_the_lock() {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        tauri::async_runtime::block_on(async {
+            let op = Operator::new(opendal::services::Memory::default())
+                .unwrap()
+                .finish();
+            let now = chrono::Utc::now().timestamp();
+            let foreign = RemoteLock {
+                device_id: "another-device".to_string(),
+                token: "token".to_string(),
+                acquired_at: now,
+                heartbeat_at: now,
+                expires_at: now + LEASE_SECS,
+            };
+            op.write(LOCK_PATH, serde_json::to_vec(&foreign).unwrap())
+                .await
+                .unwrap();
+            let result = with_remote_lock(&op, async { Ok::<_, BackendError>(()) }).await;
+            assert!(
+                matches!(result, Err(BackendError::Locked { device, .. }) if device == "another-device")
+            );
+
+            // 过期的锁会被接管，使用完后删除
+            let stale = RemoteLock {
+                expires_at: now - 1,
+                ..foreign
+            };
+            op.write(LOCK_PATH, serde_json::to_vec(&stale).unwrap())
+                .await
+                .unwrap();
+            with_remote_lock(&op, async {
+                let lock = read_lock(&op).await?.unwrap();
+                assert_eq!(lock.device_id, device_id());
+                // 同一设备上的操作可以重入
+                with_remote_lock(&op, async { Ok::<_, BackendError>(()) }).await
+            })
+            .await
+            .unwrap();
+            assert!(read_lock(&op).await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn concurrent_acquire_shares_one_token() {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        tauri::async_runtime::block_on(async {
+            let op = Operator::new(opendal::services::Memory::default())
+                .unwrap()
+                .finish();
+            // 同时获取锁时只写入一个 token，所有操作都能成功
+            let tasks: Vec<_> = (0..4)
+                .map(|_| {
+                    let op = op.clone();
+                    tauri::async_runtime::spawn(async move {
+                        with_remote_lock(&op, async {
+                            let lock = read_lock(&op).await?.unwrap();
+                            assert_eq!(lock.token, lease().token);
+                            Ok::<_, BackendError>(())
+                        })
+                        .await
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await.unwrap().unwrap();
+            }
+            assert!(read_lock(&op).await.unwrap().is_none());
+        });
+    }
+}
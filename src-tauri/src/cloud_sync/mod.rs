@@ -1,7 +1,25 @@
 mod backend;
 mod cloud_settings;
+mod encryption;
+mod journal;
+mod layout;
+mod lock;
+mod pause;
+mod prune;
+mod remote;
+mod secret_store;
+mod transfer;
 mod utils;
 
 pub use backend::Backend;
 pub use cloud_settings::CloudSettings;
-pub use utils::*;
\ No newline at end of file
+pub use encryption::EncryptionSettings;
+pub use journal::{
+    pending_operations, replay_journal, start_replay_worker, sync_later, JournalEntry,
+    PendingOperation,
+};
+pub use layout::RemoteLayout;
+pub use prune::{prune_remote, PrunedSnapshot};
+pub use remote::{delete_remote_snapshot, import_remote_game, list_remote, RemoteGame};
+pub use transfer::{CloudJobs, TransferDirection, TransferJob};
+pub use utils::*;
@@ -0,0 +1,30 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
+
+use crate::backup::Game;
+
+/// 返回列表中正在运行的游戏名，通过启动程序的路径匹配进程
+pub fn running_game(games: &[Game]) -> Option<String> {
+    let launchers: Vec<(&str, &Path)> = games
+        .iter()
+        .filter_map(|game| Some((game.name.as_str(), Path::new(game.game_path.as_ref()?))))
+        .filter(|(_, path)| !path.as_os_str().is_empty())
+        .collect();
+    if launchers.is_empty() {
+        return None;
+    }
+    let system = System::new_with_specifics(
+        RefreshKind::new().with_processes(ProcessRefreshKind::new().with_exe(UpdateKind::Always)),
+    );
+    let running: HashSet<&Path> = system
+        .processes()
+        .values()
+        .filter_map(|process| process.exe())
+        .collect();
+    launchers
+        .into_iter()
+        .find(|(_, path)| running.contains(path))
+        .map(|(name, _)| name.to_string())
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use opendal::Operator;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::backup::{expired_snapshots_per_profile, GameSnapshots, Snapshot};
+use crate::config::get_config;
+use crate::errors::BackendError;
+
+use super::layout::RemoteLayout;
+use super::lock::with_remote_lock;
+use super::remote::forget_remote_snapshots;
+use super::utils::read_game_snapshots;
+
+/// 被云端保留策略删除的存档
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrunedSnapshot {
+    pub game: String,
+    pub id: String,
+}
+
+/// Remove the remote snapshots that the remote retention setting doesn't keep
+///
+/// Only `game` is pruned when it is given, otherwise every game in the remote root.
+/// Snapshots pinned in either the remote or the local snapshots info are kept
+pub async fn prune_remote(
+    op: &Operator,
+    game: Option<&str>,
+) -> Result<Vec<PrunedSnapshot>, BackendError> {
+    let config = get_config()?;
+    let keep = config.settings.cloud_settings.remote_retention as usize;
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+    with_remote_lock(op, async {
+        let layout = RemoteLayout::from_config(&config);
+        let root = match game {
+            Some(name) => layout.remote_game_dir(name),
+            None => layout.remote_root(),
+        };
+        // 以云端实际存在的压缩包为准，而不是存档记录
+        let mut remote: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for entry in op.list_with(&root).recursive(true).await? {
+            if let Some((name, file)) = layout.parse_remote(entry.path()) {
+                if let Some(id) = file.strip_suffix(".zip") {
+                    remote
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(id.to_string());
+                }
+            }
+        }
+
+        let mut pruned = Vec::new();
+        for (name, ids) in &remote {
+            let mut pinned = HashSet::new();
+            let mut timestamps = HashMap::new();
+            let mut profiles = HashMap::new();
+            let mut collect = |info: GameSnapshots| {
+                for snapshot in info.backups {
+                    if snapshot.pinned {
+                        pinned.insert(snapshot.id.clone());
+                    }
+                    if let Some(profile) = snapshot.profile {
+                        profiles.insert(snapshot.id.clone(), profile);
+                    }
+                    timestamps.insert(snapshot.id, snapshot.timestamp);
+                }
+            };
+            match read_game_snapshots(op, &layout, name).await {
+                Ok(info) => collect(info),
+                Err(e) => {
+                    warn!(target:"rgsm::cloud::prune","Cannot read snapshots info of {}: {:?}", name, e);
+                }
+            }
+            if let Some(game) = config.games.iter().find(|g| &g.name == name) {
+                collect(game.get_game_snapshots_info()?);
+            }
+
+            // 记录中没有的存档按 id 推算创建时间，视为属于默认档案
+            let expired = expired_snapshots_per_profile(
+                ids.iter().map(|id| {
+                    let timestamp = timestamps
+                        .get(id)
+                        .copied()
+                        .unwrap_or_else(|| Snapshot::recovered(id, "").timestamp);
+                    (
+                        id.as_str(),
+                        timestamp,
+                        pinned.contains(id),
+                        profiles.get(id).map(String::as_str),
+                    )
+                }),
+                keep,
+            );
+            if expired.is_empty() {
+                continue;
+            }
+            for id in &expired {
+                info!(target:"rgsm::cloud::prune","Pruning remote snapshot {} of {}", id, name);
+                op.delete(&layout.remote_snapshot(name, id)).await?;
+            }
+            forget_remote_snapshots(op, &config, name, &expired, true).await?;
+            pruned.extend(expired.into_iter().map(|id| PrunedSnapshot {
+                game: name.clone(),
+                id: id.to_string(),
+            }));
+        }
+        Ok(pruned)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::remote::forget_in_local_info;
+    use super::super::utils::upload_candidates;
+    use super::*;
+    use crate::backup::SnapshotStorage;
+
+    #[test]
+    fn pruned_snapshots_are_not_uploaded_again() {
+        let mut info = GameSnapshots::new("Game");
+        for id in ["2024-01-01_00-00-00", "2024-01-02_00-00-00"] {
+            let mut snapshot = Snapshot::recovered(id, "");
+            snapshot.storage = SnapshotStorage::Both;
+            info.backups.push(snapshot);
+        }
+        forget_in_local_info(&mut info, &["2024-01-01_00-00-00"], true);
+        let pruned = info.find("2024-01-01_00-00-00").unwrap();
+        assert_eq!(pruned.storage, SnapshotStorage::Local);
+        assert!(pruned.remote_expired);
+
+        // 下一次同步不会再上传，云端也就不会再次清理它
+        assert!(upload_candidates(&info, true).is_empty());
+        let ids: Vec<_> = upload_candidates(&info, false)
+            .iter()
+            .map(|x| x.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["2024-01-02_00-00-00"]);
+
+        // 用户删除的云端副本不受影响，仍会重新上传
+        forget_in_local_info(&mut info, &["2024-01-02_00-00-00"], false);
+        assert_eq!(upload_candidates(&info, true).len(), 1);
+    }
+}
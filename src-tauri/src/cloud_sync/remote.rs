@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+
+use opendal::{ErrorKind, Metakey, Operator};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::backup::{GameSnapshots, SnapshotStorage};
+use crate::config::{get_config, update_config, Config};
+use crate::errors::BackendError;
+
+use super::layout::RemoteLayout;
+use super::lock::with_remote_lock;
+use super::utils::{download_config, read_game_snapshots, read_object, write_object};
+
+/// 云端的单个存档
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteSnapshot {
+    pub id: String,
+    /// 来自云端存档记录的日期和描述，记录中没有该存档时为空
+    pub date: Option<String>,
+    pub describe: Option<String>,
+    pub size: u64,
+    pub last_modified: Option<String>,
+}
+
+/// 云端的一个游戏目录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RemoteGame {
+    pub name: String,
+    pub snapshot_count: usize,
+    pub total_size: u64,
+    pub last_modified: Option<String>,
+    /// 本地配置中是否已有该游戏
+    pub in_local_config: bool,
+    pub snapshots: Vec<RemoteSnapshot>,
+}
+
+/// 列出云端根目录中的所有游戏及其存档
+pub async fn list_remote(op: &Operator) -> Result<Vec<RemoteGame>, BackendError> {
+    let config = get_config()?;
+    let layout = RemoteLayout::from_config(&config);
+    let entries = op
+        .list_with(&layout.remote_root())
+        .recursive(true)
+        .metakey(Metakey::ContentLength | Metakey::LastModified)
+        .await?;
+
+    let mut games: BTreeMap<String, RemoteGame> = BTreeMap::new();
+    for entry in entries {
+        let Some((name, file)) = layout.parse_remote(entry.path()) else {
+            continue;
+        };
+        let meta = entry.metadata();
+        let last_modified = meta.last_modified().map(|t| t.to_rfc3339());
+        let game = games.entry(name.to_string()).or_insert_with(|| RemoteGame {
+            name: name.to_string(),
+            snapshot_count: 0,
+            total_size: 0,
+            last_modified: None,
+            in_local_config: config.games.iter().any(|g| g.name == name),
+            snapshots: Vec::new(),
+        });
+        // 时间均为 UTC 的 RFC3339 格式，可以直接比较字符串
+        game.last_modified = game.last_modified.take().max(last_modified.clone());
+        if let Some(id) = file.strip_suffix(".zip") {
+            game.snapshot_count += 1;
+            game.total_size += meta.content_length();
+            game.snapshots.push(RemoteSnapshot {
+                id: id.to_string(),
+                date: None,
+                describe: None,
+                size: meta.content_length(),
+                last_modified,
+            });
+        }
+    }
+
+    // 从云端的存档记录中补充日期和描述
+    for game in games.values_mut() {
+        match read_game_snapshots(op, &layout, &game.name).await {
+            Ok(info) => {
+                for snapshot in &mut game.snapshots {
+                    if let Some(x) = info.find(&snapshot.id) {
+                        snapshot.date = Some(x.date.clone());
+                        snapshot.describe = Some(x.describe.clone());
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(target:"rgsm::cloud::remote","Cannot read snapshots info of {}: {:?}", game.name, e);
+            }
+        }
+        game.snapshots
+            .sort_by(|a, b| (&a.date, &a.id).cmp(&(&b.date, &b.id)));
+    }
+    Ok(games.into_values().collect())
+}
+
+/// 删除云端的单个存档，并更新云端和本地的存档记录
+pub async fn delete_remote_snapshot(
+    op: &Operator,
+    name: &str,
+    id: &str,
+) -> Result<(), BackendError> {
+    with_remote_lock(op, async {
+        let config = get_config()?;
+        let layout = RemoteLayout::from_config(&config);
+        info!(target:"rgsm::cloud::remote","Deleting remote snapshot {} of {}", id, name);
+        op.delete(&layout.remote_snapshot(name, id)).await?;
+
+        forget_remote_snapshots(op, &config, name, &[id], false).await?;
+        Ok(())
+    })
+    .await
+}
+
+/// 从云端和本地的存档记录中移除已在云端删除的存档
+///
+/// `pruned` 表示由云端的保留策略删除，这些存档的本地副本不会再被上传
+pub(super) async fn forget_remote_snapshots(
+    op: &Operator,
+    config: &Config,
+    name: &str,
+    ids: &[&str],
+    pruned: bool,
+) -> Result<(), BackendError> {
+    let layout = RemoteLayout::from_config(config);
+    match read_object(op, &layout.remote_snapshots_info(name)).await {
+        Ok(data) => {
+            let mut info = GameSnapshots::from_slice(&data)?;
+            info.check_writable()?;
+            info.remove(ids);
+            write_object(
+                op,
+                &layout.remote_snapshots_info(name),
+                serde_json::to_vec_pretty(&info)?,
+            )
+            .await?;
+        }
+        Err(BackendError::Cloud(e)) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    if let Some(game) = config.games.iter().find(|g| g.name == name) {
+        let _lock = game.lock_snapshots_info().await;
+        let mut info = game.get_game_snapshots_info()?;
+        forget_in_local_info(&mut info, ids, pruned);
+        game.set_game_snapshots_info(&info)?;
+    }
+    Ok(())
+}
+
+/// 本地只有云端副本的存档已无法访问，有本地副本的则变为仅在本地
+pub(super) fn forget_in_local_info(info: &mut GameSnapshots, ids: &[&str], pruned: bool) {
+    let cloud_only: Vec<String> = info
+        .backups
+        .iter()
+        .filter(|x| ids.contains(&x.id.as_str()) && x.storage == SnapshotStorage::Cloud)
+        .map(|x| x.id.clone())
+        .collect();
+    info.remove(&cloud_only.iter().map(String::as_str).collect::<Vec<_>>());
+    for snapshot in info
+        .backups
+        .iter_mut()
+        .filter(|x| ids.contains(&x.id.as_str()))
+    {
+        snapshot.storage = SnapshotStorage::Local;
+        snapshot.remote_expired |= pruned;
+    }
+}
+
+/// 将只存在于云端的游戏导入到本地配置中
+///
+/// 游戏的存档不会被下载，而是标记为仅在云端，恢复时按需下载
+pub async fn import_remote_game(op: &Operator, name: &str) -> Result<(), BackendError> {
+    with_remote_lock(op, async {
+        let config = get_config()?;
+        if config.games.iter().any(|g| g.name == name) {
+            return Err(BackendError::GameAlreadyExists(name.to_string()));
+        }
+        let layout = RemoteLayout::from_config(&config);
+        let game = download_config(op)
+            .await?
+            .games
+            .into_iter()
+            .find(|g| g.name == name)
+            .ok_or_else(|| BackendError::RemoteGameNotFound(name.to_string()))?;
+
+        let mut info = match read_game_snapshots(op, &layout, name).await {
+            Ok(info) => info,
+            Err(BackendError::Cloud(e)) if e.kind() == ErrorKind::NotFound => {
+                GameSnapshots::new(name)
+            }
+            Err(e) => return Err(e),
+        };
+        info.backups
+            .iter_mut()
+            .for_each(|x| x.storage = SnapshotStorage::Cloud);
+
+        // 加入配置和写入存档记录之间不允许其他操作写入该游戏的记录
+        let _lock = game.lock_snapshots_info().await;
+        update_config(|config| {
+            // 下载期间可能已有同名游戏加入配置
+            if config.games.iter().any(|g| g.name == name) {
+                return Err(BackendError::GameAlreadyExists(name.to_string()));
+            }
+            config.games.push(game.clone());
+            Ok(())
+        })
+        .await?;
+        game.set_game_snapshots_info(&info)?;
+        info!(target:"rgsm::cloud::remote","Imported remote game {}", name);
+        Ok(())
+    })
+    .await
+}
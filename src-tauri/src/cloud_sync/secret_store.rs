@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::app_paths;
+use crate::errors::BackendError;
+
+use super::encryption::Cipher;
+
+/// 配置文件中引用安全存储中凭据的前缀，如 `@secret:webdav.password`
+const REFERENCE_PREFIX: &str = "@secret:";
+/// 系统密钥环中使用的服务名
+#[cfg_attr(not(feature = "keyring"), allow(unused))]
+const SERVICE_NAME: &str = "rusty-game-save-manager";
+
+/// 云端凭据的存放位置
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub enum SecretStorage {
+    /// 明文保存在配置文件中
+    #[default]
+    Config,
+    /// 保存在系统的密钥环中（Linux 上为 Secret Service），
+    /// 不可用时退回到本地加密的保险库文件
+    System,
+}
+
+/// A place where credentials can be stored outside the config file
+pub trait SecretStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>, BackendError>;
+    fn set(&self, key: &str, value: &str) -> Result<(), BackendError>;
+    fn delete(&self, key: &str) -> Result<(), BackendError>;
+}
+
+/// 判断配置中的值是否为对安全存储的引用
+pub fn is_reference(value: &str) -> bool {
+    value.starts_with(REFERENCE_PREFIX)
+}
+
+/// 将凭据写入安全存储，并把配置中的值替换为引用
+///
+/// 已经是引用或为空的值不做处理
+pub fn stash(store: &dyn SecretStore, key: &str, value: &mut String) -> Result<(), BackendError> {
+    if value.is_empty() || is_reference(value) {
+        return Ok(());
+    }
+    store.set(key, value)?;
+    *value = format!("{}{}", REFERENCE_PREFIX, key);
+    Ok(())
+}
+
+/// 将引用替换为实际凭据，并从安全存储中删除该凭据
+pub fn unstash(store: &dyn SecretStore, key: &str, value: &mut String) -> Result<(), BackendError> {
+    if is_reference(value) {
+        resolve(store, value)?;
+        store.delete(key)?;
+    }
+    Ok(())
+}
+
+/// 将配置中的引用替换为安全存储中的实际凭据
+pub fn resolve(store: &dyn SecretStore, value: &mut String) -> Result<(), BackendError> {
+    if let Some(key) = value.strip_prefix(REFERENCE_PREFIX) {
+        *value = store
+            .get(key)?
+            .ok_or_else(|| BackendError::SecretNotFound(key.to_string()))?;
+    }
+    Ok(())
+}
+
+/// 获取当前设备可用的安全存储
+///
+/// 优先使用系统密钥环，不可用时使用本地加密的保险库文件
+pub fn system_store() -> &'static dyn SecretStore {
+    static STORE: OnceLock<Box<dyn SecretStore>> = OnceLock::new();
+    STORE
+        .get_or_init(|| {
+            #[cfg(feature = "keyring")]
+            if let Some(store) = KeyringStore::probe() {
+                info!(target:"rgsm::cloud::secret_store","Using system keyring to store credentials");
+                return Box::new(store);
+            }
+            info!(target:"rgsm::cloud::secret_store","Using local vault to store credentials");
+            Box::new(VaultStore::new(app_paths().vault_file()))
+        })
+        .as_ref()
+}
+
+/// Credentials stored in the OS keyring (Secret Service on Linux)
+#[cfg(feature = "keyring")]
+pub struct KeyringStore;
+
+#[cfg(feature = "keyring")]
+impl KeyringStore {
+    /// 检查系统密钥环是否可用
+    fn probe() -> Option<Self> {
+        match keyring::Entry::new(SERVICE_NAME, "probe").and_then(|entry| entry.get_password()) {
+            Ok(_) | Err(keyring::Error::NoEntry) => Some(KeyringStore),
+            Err(e) => {
+                tracing::warn!(target:"rgsm::cloud::secret_store","System keyring is not available: {:?}", e);
+                None
+            }
+        }
+    }
+
+    fn entry(key: &str) -> Result<keyring::Entry, BackendError> {
+        keyring::Entry::new(SERVICE_NAME, key).map_err(|e| BackendError::SecretStore(e.to_string()))
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl SecretStore for KeyringStore {
+    fn get(&self, key: &str) -> Result<Option<String>, BackendError> {
+        match Self::entry(key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(BackendError::SecretStore(e.to_string())),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), BackendError> {
+        Self::entry(key)?
+            .set_password(value)
+            .map_err(|e| BackendError::SecretStore(e.to_string()))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), BackendError> {
+        match Self::entry(key)?.delete_credential() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(BackendError::SecretStore(e.to_string())),
+        }
+    }
+}
+
+/// Credentials stored in an encrypted file next to the config
+///
+/// The key lives in a separate `.key` file, so this only keeps the
+/// credentials out of the config file (which is often shared or
+/// copied around), it does not protect against local attackers
+pub struct VaultStore {
+    path: PathBuf,
+    /// 同时作为读写保险库的锁，密钥派生的开销较大，读取后缓存
+    cipher: Mutex<Option<Cipher>>,
+}
+
+impl VaultStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        VaultStore {
+            path: path.into(),
+            cipher: Mutex::new(None),
+        }
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.path.with_extension("key")
+    }
+
+    /// 读取保险库的密钥，不存在时生成一个新的
+    fn read_key(&self) -> Result<Cipher, BackendError> {
+        let key_path = self.key_path();
+        let key = if key_path.exists() {
+            fs::read_to_string(&key_path)?
+        } else {
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            let key: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            write_private(&key_path, key.as_bytes())?;
+            key
+        };
+        Ok(Cipher::new(key.trim()))
+    }
+
+    fn with_cipher<T, F>(&self, f: F) -> Result<T, BackendError>
+    where
+        F: FnOnce(&Cipher) -> Result<T, BackendError>,
+    {
+        let mut cached = self.cipher.lock().unwrap_or_else(PoisonError::into_inner);
+        if cached.is_none() {
+            *cached = Some(self.read_key()?);
+        }
+        // safe: 上面已经确保有值
+        f(cached.as_ref().unwrap())
+    }
+
+    fn load(&self, cipher: &Cipher) -> Result<HashMap<String, String>, BackendError> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let data = cipher.decrypt(&fs::read(&self.path)?).map_err(|e| {
+            BackendError::SecretStore(format!("Cannot open vault, the key may be lost: {}", e))
+        })?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn save(&self, cipher: &Cipher, secrets: &HashMap<String, String>) -> Result<(), BackendError> {
+        let data = cipher.encrypt(&serde_json::to_vec(secrets)?)?;
+        write_private(&self.path, &data)?;
+        Ok(())
+    }
+
+    fn update<F>(&self, f: F) -> Result<(), BackendError>
+    where
+        F: FnOnce(&mut HashMap<String, String>),
+    {
+        self.with_cipher(|cipher| {
+            let mut secrets = self.load(cipher)?;
+            f(&mut secrets);
+            self.save(cipher, &secrets)
+        })
+    }
+}
+
+/// 写入只有当前用户可以读写的文件，已有文件的权限也会被收紧
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(data)
+}
+
+impl SecretStore for VaultStore {
+    fn get(&self, key: &str) -> Result<Option<String>, BackendError> {
+        self.with_cipher(|cipher| Ok(self.load(cipher)?.remove(key)))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), BackendError> {
+        self.update(|secrets| {
+            secrets.insert(key.to_string(), value.to_string());
+        })
+    }
+
+    fn delete(&self, key: &str) -> Result<(), BackendError> {
+        self.update(|secrets| {
+            secrets.remove(key);
+        })
+    }
+}
+
+/// In-memory store, used in tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore(Mutex<HashMap<String, String>>);
+
+#[cfg(test)]
+impl SecretStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<String>, BackendError> {
+        Ok(self.0.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), BackendError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), BackendError> {
+        self.0.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cloud_sync::{Backend, CloudSettings};
+
+    #[test]
+    fn stash_and_resolve_credentials() {
+        let store = MemoryStore::default();
+        let mut settings = CloudSettings {
+            backend: Backend::WebDAV {
+                endpoint: "https://dav".to_string(),
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            },
+            ..Default::default()
+        };
+        settings.stash_secrets(&store).unwrap();
+        let stored = serde_json::to_string(&settings).unwrap();
+        assert!(!stored.contains("\"secret\""));
+        assert!(stored.contains("@secret:webdav.password"));
+
+        // 重复保存不会覆盖已有的凭据
+        settings.stash_secrets(&store).unwrap();
+        settings.unstash_secrets(&store).unwrap();
+        let Backend::WebDAV { password, .. } = &settings.backend else {
+            panic!("backend changed")
+        };
+        assert_eq!(password, "secret");
+        assert_eq!(store.get("webdav.password").unwrap(), None);
+    }
+
+    #[test]
+    fn missing_secret_is_an_error() {
+        let mut value = "@secret:s3.secret_access_key".to_string();
+        assert!(matches!(
+            resolve(&MemoryStore::default(), &mut value),
+            Err(BackendError::SecretNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn vault_round_trip() {
+        let dir = std::env::temp_dir().join(format!("rgsm_vault_test_{}", ulid::Ulid::new()));
+        fs::create_dir_all(&dir).unwrap();
+        let vault = VaultStore::new(dir.join("test.vault"));
+        vault.set("webdav.password", "secret").unwrap();
+        assert!(!fs::read(dir.join("test.vault"))
+            .unwrap()
+            .windows(6)
+            .any(|w| w == b"secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in ["test.vault", "test.key"] {
+                let mode = fs::metadata(dir.join(file)).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+        }
+        let reopened = VaultStore::new(dir.join("test.vault"));
+        assert_eq!(
+            reopened.get("webdav.password").unwrap().as_deref(),
+            Some("secret")
+        );
+        reopened.delete("webdav.password").unwrap();
+        assert_eq!(reopened.get("webdav.password").unwrap(), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::errors::BackendError;
+
+/// 传输方向
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+/// A single file to transfer in a cloud job
+#[derive(Debug, Clone)]
+pub struct TransferTask {
+    /// 文件所属的游戏
+    pub game: String,
+    pub local_path: PathBuf,
+    pub remote_path: String,
+    /// 云端存储的大小（加密后的大小）
+    pub size: u64,
+}
+
+/// 云同步任务的进度，通过 `CloudProgress` 事件发送给前端
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransferProgress {
+    pub job_id: String,
+    pub direction: TransferDirection,
+    /// 当前正在传输的游戏
+    pub game: Option<String>,
+    /// 当前正在传输的文件
+    pub current: Option<String>,
+    pub transferred_bytes: u64,
+    pub total_bytes: u64,
+    pub finished_objects: usize,
+    pub total_objects: usize,
+    /// 各游戏已传输的字节数
+    pub per_game_bytes: HashMap<String, u64>,
+    pub done: bool,
+}
+
+/// A running upload/download job, reports progress and can be cancelled
+pub struct TransferJob {
+    progress: Mutex<TransferProgress>,
+    cancelled: Arc<AtomicBool>,
+    app_handle: Option<AppHandle>,
+}
+
+impl TransferJob {
+    pub fn new(direction: TransferDirection, app_handle: Option<AppHandle>) -> Self {
+        // 同一毫秒内开始的任务也不会重复
+        let job_id = format!("{:?}-{}", direction, ulid::Ulid::new());
+        TransferJob {
+            progress: Mutex::new(TransferProgress {
+                job_id,
+                direction,
+                game: None,
+                current: None,
+                transferred_bytes: 0,
+                total_bytes: 0,
+                finished_objects: 0,
+                total_objects: 0,
+                per_game_bytes: HashMap::new(),
+                done: false,
+            }),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            app_handle,
+        }
+    }
+
+    pub fn id(&self) -> String {
+        self.lock().job_id.clone()
+    }
+
+    pub fn cancel_token(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 任务被取消时返回错误，用于在传输的间隙中止任务
+    pub fn check_cancelled(&self) -> Result<(), BackendError> {
+        if self.is_cancelled() {
+            Err(BackendError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 开始传输，记录需要传输的文件总数和总大小
+    pub fn start(&self, tasks: &[TransferTask]) {
+        let mut progress = self.lock();
+        progress.total_objects = tasks.len();
+        progress.total_bytes = tasks.iter().map(|t| t.size).sum();
+        self.emit(&progress);
+    }
+
+    /// 记录某个文件新传输的字节数
+    pub fn advance(&self, task: &TransferTask, bytes: u64) {
+        let mut progress = self.lock();
+        progress.game = Some(task.game.clone());
+        progress.current = Some(task.remote_path.clone());
+        progress.transferred_bytes += bytes;
+        *progress
+            .per_game_bytes
+            .entry(task.game.clone())
+            .or_default() += bytes;
+        self.emit(&progress);
+    }
+
+    /// 记录某个文件已经传输完成
+    pub fn finish_object(&self) {
+        let mut progress = self.lock();
+        progress.finished_objects += 1;
+        self.emit(&progress);
+    }
+
+    pub fn finish(&self) {
+        let mut progress = self.lock();
+        progress.done = true;
+        progress.current = None;
+        self.emit(&progress);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TransferProgress> {
+        self.progress.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn emit(&self, progress: &TransferProgress) {
+        if let Some(app_handle) = &self.app_handle {
+            if let Err(e) = app_handle.emit_all("CloudProgress", progress.clone()) {
+                warn!(target:"rgsm::cloud::transfer","Cannot emit cloud progress: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Cancellation tokens of the running cloud jobs, managed by tauri
+#[derive(Default)]
+pub struct CloudJobs(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl CloudJobs {
+    pub fn register(&self, job: &TransferJob) {
+        self.lock().insert(job.id(), job.cancel_token());
+    }
+
+    pub fn remove(&self, job_id: &str) {
+        self.lock().remove(job_id);
+    }
+
+    /// 取消任务，任务不存在时返回 `false`
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.lock().get(job_id) {
+            Some(token) => {
+                token.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
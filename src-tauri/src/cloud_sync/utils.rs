@@ -1,77 +1,375 @@
 use std::fs;
+use std::time::{Duration, Instant};
 
-use opendal::Operator;
+use opendal::{ErrorKind, Operator};
 use tracing::info;
 
-use crate::backup::GameSnapshots;
-use crate::config::{get_config, set_config, Config};
+use crate::backup::{Game, GameSnapshots, Snapshot, SnapshotStorage};
+use crate::config::{app_paths, get_config, update_config, Config, SyncedConfig};
 use crate::errors::BackendError;
 
-pub async fn upload_all(op: &Operator) -> Result<(), BackendError> {
-    let config = get_config()?;
-    // 上传配置文件
-    upload_config(op).await?;
-    // 依次上传所有游戏的存档记录和存档
-    for game in config.games {
-        // !NOTICE: 这个地方必须硬编码，因为云端目录必须固定
-        let cloud_backup_path = format!("save_data/{}", game.name);
-        let backup_info = game.get_game_snapshots_info()?;
-        // 写入存档记录
-        op.write(
-            &format!("{}/Backups.json", &cloud_backup_path),
-            serde_json::to_string_pretty(&backup_info)?,
-        )
+use super::encryption::{get_cipher, is_encrypted, ENCRYPTION_OVERHEAD};
+use super::layout::RemoteLayout;
+use super::lock::with_remote_lock;
+use super::transfer::{TransferJob, TransferTask};
+
+/// 分块上传时每块的大小
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// 云端的共享配置文件，只包含可以在设备间同步的部分
+const SYNCED_CONFIG_PATH: &str = "/GameSaveManager.sync.json";
+/// 旧版本上传的完整配置文件，其中包含了云端凭据
+const LEGACY_CONFIG_PATH: &str = "/GameSaveManager.config.json";
+
+pub async fn upload_all(op: &Operator, job: &TransferJob) -> Result<(), BackendError> {
+    // 上传期间持有云端的锁，避免与其他设备同时写入
+    with_remote_lock(op, async {
+        let config = get_config()?;
+        let layout = RemoteLayout::from_config(&config);
+        // 上传配置文件
+        upload_config(op).await?;
+        // 依次上传所有游戏的存档记录，并统计需要上传的存档
+        let mut tasks = Vec::new();
+        for game in &config.games {
+            tasks.extend(prepare_game_upload(op, &layout, game, false).await?);
+        }
+        upload_tasks(op, &tasks, job).await?;
+        // 全部上传完成后，本地的存档在云端也有了副本
+        for game in &get_config()?.games {
+            mark_uploaded(game).await?;
+        }
+        job.finish();
+        Ok(())
+    })
+    .await
+}
+
+/// 只上传单个游戏的存档记录和尚未上传的存档
+pub async fn upload_game(
+    op: &Operator,
+    game: &Game,
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    with_remote_lock(op, async {
+        let layout = RemoteLayout::from_config(&get_config()?);
+        let tasks = prepare_game_upload(op, &layout, game, true).await?;
+        upload_tasks(op, &tasks, job).await?;
+        mark_uploaded(game).await?;
+        job.finish();
+        Ok(())
+    })
+    .await
+}
+
+/// 上传游戏的存档记录，并返回需要上传的存档
+///
+/// `only_pending` 为真时跳过已经上传过的存档
+async fn prepare_game_upload(
+    op: &Operator,
+    layout: &RemoteLayout,
+    game: &Game,
+    only_pending: bool,
+) -> Result<Vec<TransferTask>, BackendError> {
+    // 等待正在写入的记录（如导入游戏时移动文件）完成
+    let mut backup_info = {
+        let _lock = game.lock_snapshots_info().await;
+        game.get_game_snapshots_info()?
+    };
+    backup_info.check_writable()?;
+    // 云端已清理的存档不再出现在云端的记录中
+    backup_info.backups.retain(|x| !x.remote_expired);
+    // 写入存档记录
+    write_object(
+        op,
+        &layout.remote_snapshots_info(&game.name),
+        serde_json::to_vec_pretty(&backup_info)?,
+    )
+    .await?;
+    // 写入存档zip文件（不包括额外备份）
+    let mut tasks = Vec::new();
+    for backup in upload_candidates(&backup_info, only_pending) {
+        let local_path = layout.local_snapshot(&game.name, &backup.id);
+        tasks.push(TransferTask {
+            game: game.name.clone(),
+            size: stored_len(fs::metadata(&local_path)?.len())?,
+            local_path,
+            remote_path: layout.remote_snapshot(&game.name, &backup.id),
+        });
+    }
+    Ok(tasks)
+}
+
+/// 需要上传压缩包的存档
+///
+/// 仅存在于云端和已被云端保留策略删除的存档不会上传，`only_pending` 时也跳过云端已有的
+pub(super) fn upload_candidates(info: &GameSnapshots, only_pending: bool) -> Vec<&Snapshot> {
+    info.backups
+        .iter()
+        .filter(|x| {
+            if only_pending {
+                x.needs_upload()
+            } else {
+                x.storage != SnapshotStorage::Cloud && !x.remote_expired
+            }
+        })
+        .collect()
+}
+
+async fn upload_tasks(
+    op: &Operator,
+    tasks: &[TransferTask],
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    job.start(tasks);
+    for task in tasks {
+        job.check_cancelled()?;
+        info!(target:"rgsm::cloud::utils","Uploading {}", task.remote_path);
+        upload_file(op, task, job).await?;
+        job.finish_object();
+    }
+    Ok(())
+}
+
+/// 将本地的存档标记为云端也有副本
+async fn mark_uploaded(game: &Game) -> Result<(), BackendError> {
+    let _lock = game.lock_snapshots_info().await;
+    let mut backup_info = game.get_game_snapshots_info()?;
+    backup_info
+        .backups
+        .iter_mut()
+        .filter(|x| x.needs_upload())
+        .for_each(|x| x.storage = SnapshotStorage::Both);
+    game.set_game_snapshots_info(&backup_info)?;
+    Ok(())
+}
+
+pub async fn download_all(op: &Operator, job: &TransferJob) -> Result<(), BackendError> {
+    // 下载期间同样持有锁，避免读到其他设备写入一半的存档
+    with_remote_lock(op, async {
+        // 下载配置文件，并与本地配置合并
+        let synced = download_config(op).await?;
+        let config = update_config(|config| {
+            config.apply_synced(synced);
+            Ok::<Config, BackendError>(config.clone())
+        })
         .await?;
-        // 写入存档zip文件（不包括额外备份）
-        for backup in backup_info.backups {
-            // TODO: 此处的cloud_backup_path应当改为本地的路径
-            let save_path = format!("{}/{}.zip", &cloud_backup_path, backup.date);
-            info!(target:"rgsm::cloud::utils","Uploading {}", save_path);
-            op.write(&save_path, fs::read(&save_path)?).await?;
+        let layout = RemoteLayout::from_config(&config);
+        // 依次下载所有游戏的存档记录，并统计需要下载的存档
+        let mut tasks = Vec::new();
+        for game in &config.games {
+            tasks.extend(prepare_game_download(op, &layout, game).await?);
+        }
+        download_tasks(op, &tasks, job).await
+    })
+    .await
+}
+
+/// 只下载单个游戏的存档记录和存档
+pub async fn download_game(
+    op: &Operator,
+    game: &Game,
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    with_remote_lock(op, async {
+        let layout = RemoteLayout::from_config(&get_config()?);
+        let tasks = prepare_game_download(op, &layout, game).await?;
+        download_tasks(op, &tasks, job).await
+    })
+    .await
+}
+
+/// 读取云端的存档记录，其中的路径会被改为本设备上的路径
+pub(super) async fn read_game_snapshots(
+    op: &Operator,
+    layout: &RemoteLayout,
+    name: &str,
+) -> Result<GameSnapshots, BackendError> {
+    let backup_info = read_object(op, &layout.remote_snapshots_info(name)).await?;
+    let mut backup_info = GameSnapshots::from_slice(&backup_info)?;
+    layout.localize(&mut backup_info);
+    Ok(backup_info)
+}
+
+/// 下载游戏的存档记录，并返回需要下载的存档
+async fn prepare_game_download(
+    op: &Operator,
+    layout: &RemoteLayout,
+    game: &Game,
+) -> Result<Vec<TransferTask>, BackendError> {
+    let mut backup_info = read_game_snapshots(op, layout, &game.name).await?;
+    let lock = game.lock_snapshots_info().await;
+    // 本地已经移除压缩包的存档保持仅在云端，其余的都会被下载
+    let evicted: Vec<String> = game
+        .get_game_snapshots_info()
+        .map(|local| {
+            local
+                .backups
+                .into_iter()
+                .filter(|x| x.storage == SnapshotStorage::Cloud)
+                .map(|x| x.id)
+                .collect()
+        })
+        .unwrap_or_default();
+    backup_info.backups.iter_mut().for_each(|x| {
+        x.storage = if evicted.contains(&x.id) {
+            SnapshotStorage::Cloud
+        } else {
+            SnapshotStorage::Both
+        }
+    });
+    // 写入存档记录
+    game.set_game_snapshots_info(&backup_info)?;
+    drop(lock);
+    // 写入存档zip文件（不包括额外备份）
+    let mut tasks = Vec::new();
+    for backup in backup_info.backups {
+        if backup.storage == SnapshotStorage::Cloud {
+            continue;
         }
+        let remote_path = layout.remote_snapshot(&game.name, &backup.id);
+        tasks.push(TransferTask {
+            game: game.name.clone(),
+            size: op.stat(&remote_path).await?.content_length(),
+            local_path: layout.local_snapshot(&game.name, &backup.id),
+            remote_path,
+        });
     }
+    Ok(tasks)
+}
+
+async fn download_tasks(
+    op: &Operator,
+    tasks: &[TransferTask],
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    job.start(tasks);
+    for task in tasks {
+        job.check_cancelled()?;
+        info!(target:"rgsm::cloud::utils","Downloading {}", task.remote_path);
+        download_file(op, task, job).await?;
+        job.finish_object();
+    }
+    job.finish();
     Ok(())
 }
 
-pub async fn download_all(op: &Operator) -> Result<(), BackendError> {
-    // 下载配置文件
-    let config = String::from_utf8(op.read("/GameSaveManager.config.json").await?.to_vec())?;
-    let config: Config = serde_json::from_str(&config)?;
-    set_config(&config).await?;
-    // 依次下载所有游戏的存档记录和存档
-    for game in config.games {
-        // !NOTICE: 这个地方必须硬编码，因为云端目录必须固定
-        let backup_path = format!("save_data/{}", game.name);
-        let backup_info = op
-            .read(&format!("{}/Backups.json", &backup_path))
-            .await?
-            .to_vec();
-        let backup_info: GameSnapshots = serde_json::from_str(&String::from_utf8(backup_info)?)?;
-        game.set_game_snapshots_info(&backup_info)?;
-        // 写入存档记录
-        // TODO: 此处的cloud_backup_path应当改为本地的路径
-        fs::write(
-            &format!("{}/Backups.json", &backup_path),
-            serde_json::to_string_pretty(&backup_info)?,
-        )?;
-        // 写入存档zip文件（不包括额外备份）
-        for backup in backup_info.backups {
-            let save_path = format!("{}/{}.zip", &backup_path, backup.date);
-            info!(target:"rgsm::cloud::utils","Downloading {}", save_path);
-            let data = op.read(&save_path).await?.to_vec();
-            fs::write(&save_path, &data)?;
+/// 分块上传单个文件，云端已有相同大小的文件时跳过
+///
+/// 后端支持追加写入时可以续传，见 `upload_resumable`，
+/// 否则中断或取消的文件下次会从头上传
+async fn upload_file(
+    op: &Operator,
+    task: &TransferTask,
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    if let Ok(meta) = op.stat(&task.remote_path).await {
+        if meta.content_length() == task.size {
+            info!(target:"rgsm::cloud::utils","Skip uploaded {}", task.remote_path);
+            job.advance(task, task.size);
+            return Ok(());
+        }
+    }
+    let capability = op.info().full_capability();
+    if capability.write_can_append && (capability.rename || capability.copy) {
+        return upload_resumable(op, task, job).await;
+    }
+    let data = seal(fs::read(&task.local_path)?)?;
+    let mut writer = op.writer_with(&task.remote_path).chunk(CHUNK_SIZE).await?;
+    for chunk in data.chunks(write_size()?) {
+        if job.is_cancelled() {
+            writer.abort().await?;
+            return Err(BackendError::Cancelled);
         }
+        writer.write(chunk.to_vec()).await?;
+        job.advance(task, chunk.len() as u64);
     }
+    writer.close().await?;
+    Ok(())
+}
+
+/// 分块追加到云端的 `.part` 文件，中断或取消后从已上传的长度继续，完成后移动到目标位置
+///
+/// 加密后的数据缓存在本地，续传时追加的内容与已上传的部分一致
+async fn upload_resumable(
+    op: &Operator,
+    task: &TransferTask,
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    let part_path = [task.remote_path.as_str(), ".part"].concat();
+    let cache_dir = app_paths().upload_cache_dir();
+    let cache_path = cache_dir.join(task.remote_path.trim_start_matches('/').replace('/', "_"));
+    let data = match fs::read(&cache_path) {
+        Ok(data) if data.len() as u64 == task.size => data,
+        _ => {
+            // 没有可用的缓存时，云端已上传的部分无法继续使用
+            let data = seal(fs::read(&task.local_path)?)?;
+            fs::create_dir_all(&cache_dir)?;
+            fs::write(&cache_path, &data)?;
+            op.delete(&part_path).await?;
+            data
+        }
+    };
+    let uploaded = match op.stat(&part_path).await {
+        Ok(meta) if meta.content_length() <= data.len() as u64 => meta.content_length(),
+        Ok(_) => {
+            op.delete(&part_path).await?;
+            0
+        }
+        Err(_) => 0,
+    };
+    if uploaded > 0 {
+        info!(target:"rgsm::cloud::utils","Resuming {} from {} bytes", task.remote_path, uploaded);
+        job.advance(task, uploaded);
+    }
+    for chunk in data[uploaded as usize..].chunks(write_size()?) {
+        job.check_cancelled()?;
+        // 每块单独提交，中断时已提交的部分保留在云端
+        let mut writer = op.writer_with(&part_path).append(true).await?;
+        writer.write(chunk.to_vec()).await?;
+        writer.close().await?;
+        job.advance(task, chunk.len() as u64);
+    }
+    if op.info().full_capability().rename {
+        op.rename(&part_path, &task.remote_path).await?;
+    } else {
+        op.copy(&part_path, &task.remote_path).await?;
+        op.delete(&part_path).await?;
+    }
+    fs::remove_file(&cache_path)?;
+    Ok(())
+}
+
+/// 下载单个文件，本地已有相同大小的文件时跳过
+async fn download_file(
+    op: &Operator,
+    task: &TransferTask,
+    job: &TransferJob,
+) -> Result<(), BackendError> {
+    if let Ok(meta) = fs::metadata(&task.local_path) {
+        if stored_len(meta.len())? == task.size {
+            info!(target:"rgsm::cloud::utils","Skip downloaded {}", task.local_path.display());
+            job.advance(task, task.size);
+            return Ok(());
+        }
+    }
+    let data = read_object(op, &task.remote_path).await?;
+    job.advance(task, task.size);
+    // 先写入临时文件再重命名，避免中断时留下不完整的文件
+    let mut tmp_path = task.local_path.clone().into_os_string();
+    tmp_path.push(".part");
+    fs::write(&tmp_path, &data)?;
+    fs::rename(&tmp_path, &task.local_path)?;
     Ok(())
 }
 
 /// 上传单个游戏的配置文件
 pub async fn upload_game_snapshots(op: &Operator, info: GameSnapshots) -> Result<(), BackendError> {
-    // !NOTICE: 这个地方必须硬编码，因为云端目录必须固定
-    let backup_path = format!("save_data/{}", info.name);
-    op.write(
-        &format!("{}/Backups.json", &backup_path),
-        serde_json::to_string_pretty(&info)?,
+    info.check_writable()?;
+    let layout = RemoteLayout::from_config(&get_config()?);
+    write_object(
+        op,
+        &layout.remote_snapshots_info(&info.name),
+        serde_json::to_vec_pretty(&info)?,
     )
     .await?;
     Ok(())
@@ -79,13 +377,152 @@ pub async fn upload_game_snapshots(op: &Operator, info: GameSnapshots) -> Result
 
 // 上传配置文件
 pub async fn upload_config(op: &Operator) -> Result<(), BackendError> {
-    // !NOTICE: 这个地方必须硬编码，因为云端目录必须固定
-    let config = get_config()?;
-    // 上传配置文件
-    op.write(
-        "/GameSaveManager.config.json",
-        serde_json::to_string_pretty(&config)?,
-    )
-    .await?;
+    let config = SyncedConfig::from(&get_config()?);
+    // 上传配置文件，仅包含可同步的部分
+    write_object(op, SYNCED_CONFIG_PATH, serde_json::to_vec_pretty(&config)?).await?;
+    // 删除旧版本上传的完整配置文件，避免凭据残留在云端
+    op.delete(LEGACY_CONFIG_PATH).await?;
+    Ok(())
+}
+
+/// 下载云端的共享配置
+///
+/// 若云端只有旧版本上传的完整配置，则从中提取可同步的部分，
+/// 并迁移为新的格式
+pub(super) async fn download_config(op: &Operator) -> Result<SyncedConfig, BackendError> {
+    match read_object(op, SYNCED_CONFIG_PATH).await {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(BackendError::Cloud(e)) if e.kind() == ErrorKind::NotFound => {
+            info!(target:"rgsm::cloud::utils","Migrating legacy cloud config");
+            let data = read_object(op, LEGACY_CONFIG_PATH).await?;
+            let synced: SyncedConfig = serde_json::from_slice(&data)?;
+            write_object(op, SYNCED_CONFIG_PATH, serde_json::to_vec_pretty(&synced)?).await?;
+            op.delete(LEGACY_CONFIG_PATH).await?;
+            Ok(synced)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 写入云端文件，启用加密时会先在本地加密
+pub async fn write_object(op: &Operator, path: &str, data: Vec<u8>) -> Result<(), BackendError> {
+    let data = seal(data)?;
+    let mut writer = op.writer_with(path).chunk(CHUNK_SIZE).await?;
+    for chunk in data.chunks(write_size()?) {
+        writer.write(chunk.to_vec()).await?;
+    }
+    writer.close().await?;
     Ok(())
 }
+
+/// 每次写入的大小，限速时不能超过 ThrottleLayer 的令牌桶容量
+fn write_size() -> Result<usize, BackendError> {
+    let settings = get_config()?.settings.cloud_settings;
+    Ok(settings
+        .upload_bandwidth()
+        .map_or(CHUNK_SIZE, |bandwidth| CHUNK_SIZE.min(bandwidth as usize)))
+}
+
+/// 启用加密时加密数据，否则原样返回
+fn seal(data: Vec<u8>) -> Result<Vec<u8>, BackendError> {
+    let settings = get_config()?.settings.cloud_settings.encryption;
+    match get_cipher(&settings)? {
+        Some(cipher) => cipher.encrypt(&data),
+        None => Ok(data),
+    }
+}
+
+/// 本地文件上传到云端后的大小
+fn stored_len(len: u64) -> Result<u64, BackendError> {
+    if get_config()?.settings.cloud_settings.encryption.enabled {
+        Ok(len + ENCRYPTION_OVERHEAD)
+    } else {
+        Ok(len)
+    }
+}
+
+/// 读取云端文件，若文件经过加密则自动解密
+///
+/// 未加密的文件原样返回，以兼容开启加密前上传的文件
+pub async fn read_object(op: &Operator, path: &str) -> Result<Vec<u8>, BackendError> {
+    let settings = get_config()?.settings.cloud_settings;
+    let data = match settings.download_bandwidth() {
+        Some(bandwidth) => read_throttled(op, path, bandwidth as u64).await?,
+        None => op.read(path).await?.to_vec(),
+    };
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    match get_cipher(&settings.encryption)? {
+        Some(cipher) => cipher.decrypt(&data),
+        None => Err(BackendError::MissingPassphrase),
+    }
+}
+
+/// 按限速分段读取云端文件，opendal 的 ThrottleLayer 不能限制读取
+async fn read_throttled(
+    op: &Operator,
+    path: &str,
+    bandwidth: u64,
+) -> Result<Vec<u8>, BackendError> {
+    let len = op.stat(path).await?.content_length();
+    let mut data = Vec::with_capacity(len as usize);
+    let mut start = 0;
+    while start < len {
+        let begin = Instant::now();
+        // 每秒读取不超过限速的字节数
+        let end = (start + bandwidth).min(len);
+        data.extend(op.read_with(path).range(start..end).await?.to_vec());
+        start = end;
+        if start < len {
+            if let Some(rest) = Duration::from_secs(1).checked_sub(begin.elapsed()) {
+                tokio::time::sleep(rest).await;
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cloud_sync::TransferDirection;
+    use crate::config::init_test_config;
+
+    #[test]
+    fn resume_interrupted_upload() {
+        init_test_config();
+        let root = app_paths().upload_cache_dir().with_file_name("remote");
+        fs::create_dir_all(&root).unwrap();
+        let mut builder = opendal::services::Fs::default();
+        builder.root(&root.to_string_lossy());
+        let op = Operator::new(builder).unwrap().finish();
+
+        let data: Vec<u8> = (0..100).collect();
+        let local_path = root.with_file_name("upload.zip");
+        fs::write(&local_path, &data).unwrap();
+        let task = TransferTask {
+            game: "Game".to_string(),
+            local_path,
+            remote_path: "Game/upload.zip".to_string(),
+            size: data.len() as u64,
+        };
+        // 上次上传在 40 字节处中断，待上传的数据还在缓存中
+        let cache_path = app_paths().upload_cache_dir().join("Game_upload.zip");
+        fs::create_dir_all(app_paths().upload_cache_dir()).unwrap();
+        fs::write(&cache_path, &data).unwrap();
+        tauri::async_runtime::block_on(async {
+            // 用标记代替已上传的部分，确认这部分不会重新上传
+            op.write("Game/upload.zip.part", vec![u8::MAX; 40])
+                .await
+                .unwrap();
+            let job = TransferJob::new(TransferDirection::Upload, None);
+            upload_file(&op, &task, &job).await.unwrap();
+            let uploaded = op.read("Game/upload.zip").await.unwrap().to_vec();
+            assert_eq!(uploaded[..40], [u8::MAX; 40]);
+            assert_eq!(uploaded[40..], data[40..]);
+            assert!(!op.is_exist("Game/upload.zip.part").await.unwrap());
+        });
+        assert!(!cache_path.exists());
+    }
+}
@@ -50,6 +50,7 @@ impl Default for Config {
                 home_page: default_value::default_home_page(),
                 log_to_file: true,
                 add_new_to_favorites: false,
+                local_retention: 0,
             },
             favorites: vec![],
             quick_action: QuickActionsSettings::default(),
@@ -0,0 +1,161 @@
+use semver::Version;
+use serde_json::{Map, Value};
+
+use super::{Config, QuickActionsSettings};
+use crate::errors::ConfigError;
+
+/// 能够迁移的最旧的配置版本
+const MIN_CONFIG_VERSION: &str = "1.0.0";
+
+/// One step of the config migration chain
+///
+/// `migrate` upgrades a config older than `version` to `version`
+struct ConfigMigration {
+    version: &'static str,
+    migrate: fn(&mut Map<String, Value>) -> Result<(), ConfigError>,
+}
+
+/// 按版本从低到高排列，配置会依次经过所有比它新的步骤
+///
+/// 新增的字段若有 serde 默认值则不需要迁移，
+/// 只有重命名、移动或改变含义的字段才需要在这里添加一步
+const CONFIG_MIGRATIONS: &[ConfigMigration] = &[ConfigMigration {
+    version: "1.4.0",
+    migrate: migrate_to_1_4_0,
+}];
+
+/// The result of `migrate_config`
+#[derive(Debug, PartialEq)]
+pub enum MigrationOutcome {
+    /// 版本与软件一致，无需处理
+    UpToDate,
+    /// 已从 `from` 迁移到当前版本
+    Migrated { from: Version },
+    /// 配置由更新的软件写入，只能以只读模式使用
+    Newer { version: Version },
+}
+
+/// 读取配置的版本号，兼容早期的 "1.0.0 alpha" 写法
+fn config_version(config: &Map<String, Value>) -> Result<Version, ConfigError> {
+    let version = config
+        .get("version")
+        .and_then(Value::as_str)
+        .ok_or_else(|| ConfigError::UnsupportedVersion("unknown".to_string()))?;
+    let version = if version == "1.0.0 alpha" {
+        "1.0.0-alpha"
+    } else {
+        version
+    };
+    Ok(Version::parse(version)?)
+}
+
+/// Upgrade a raw config to `current` by running every migration newer than it
+///
+/// Works on the JSON before it is deserialized, so that fields which no longer
+/// exist in `Config` can still be read
+pub fn migrate_config(
+    config: &mut Value,
+    current: &Version,
+) -> Result<MigrationOutcome, ConfigError> {
+    let config = config
+        .as_object_mut()
+        .ok_or_else(|| ConfigError::UnsupportedVersion("unknown".to_string()))?;
+    let from = config_version(config)?;
+    if &from > current {
+        return Ok(MigrationOutcome::Newer { version: from });
+    }
+    if from < Version::parse(MIN_CONFIG_VERSION)? {
+        return Err(ConfigError::UnsupportedVersion(from.to_string()));
+    }
+    if &from == current {
+        return Ok(MigrationOutcome::UpToDate);
+    }
+    for step in CONFIG_MIGRATIONS {
+        let version = Version::parse(step.version)?;
+        if from < version && &version <= current {
+            (step.migrate)(config)?;
+            config.insert("version".to_string(), version.to_string().into());
+        }
+    }
+    config.insert("version".to_string(), current.to_string().into());
+    Ok(MigrationOutcome::Migrated { from })
+}
+
+/// Parse a config written by this or an older version, such as one in an exported library
+pub fn parse_config(mut raw: Value) -> Result<Config, ConfigError> {
+    let current = Version::parse(&Config::default().version)?;
+    if let MigrationOutcome::Newer { version } = migrate_config(&mut raw, &current)? {
+        return Err(ConfigError::UnsupportedVersion(version.to_string()));
+    }
+    Ok(serde_json::from_value(raw)?)
+}
+
+/// 1.0 到 1.4 之间新增了收藏夹和快捷操作，补全缺失的部分
+fn migrate_to_1_4_0(config: &mut Map<String, Value>) -> Result<(), ConfigError> {
+    config
+        .entry("favorites")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if !config.contains_key("quick_action") {
+        config.insert(
+            "quick_action".to_string(),
+            serde_json::to_value(QuickActionsSettings::default())?,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrate_to_1_4_0_fills_missing_sections() {
+        let mut config = json!({"version": "1.0.0", "favorites": [{"label": "Game"}]});
+        migrate_to_1_4_0(config.as_object_mut().unwrap()).unwrap();
+        // 已有的收藏夹保持不变
+        assert_eq!(config["favorites"], json!([{"label": "Game"}]));
+        assert_eq!(
+            config["quick_action"],
+            serde_json::to_value(QuickActionsSettings::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn migration_chain() {
+        let current = Version::parse("1.4.0").unwrap();
+
+        let mut config = json!({"version": "1.2.0"});
+        assert_eq!(
+            migrate_config(&mut config, &current).unwrap(),
+            MigrationOutcome::Migrated {
+                from: Version::parse("1.2.0").unwrap()
+            }
+        );
+        assert_eq!(config["version"], "1.4.0");
+        assert!(config["favorites"].is_array());
+
+        let mut config = json!({"version": "1.4.0"});
+        assert_eq!(
+            migrate_config(&mut config, &current).unwrap(),
+            MigrationOutcome::UpToDate
+        );
+
+        // 更新的配置保持原样
+        let mut config = json!({"version": "2.0.0"});
+        assert_eq!(
+            migrate_config(&mut config, &current).unwrap(),
+            MigrationOutcome::Newer {
+                version: Version::parse("2.0.0").unwrap()
+            }
+        );
+        assert_eq!(config["version"], "2.0.0");
+
+        // 1.0 之前的配置无法迁移
+        let mut config = json!({"version": "1.0.0 alpha"});
+        assert!(matches!(
+            migrate_config(&mut config, &current),
+            Err(ConfigError::UnsupportedVersion(v)) if v == "1.0.0-alpha"
+        ));
+    }
+}
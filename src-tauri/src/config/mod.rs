@@ -1,9 +1,21 @@
 mod app_config;
+mod migration;
+mod paths;
 mod quick_actions_settings;
+mod recovery;
 mod settings;
+mod store;
+mod synced_config;
 mod utils;
 
-pub use app_config::Config;
+pub use app_config::{Config, FavoriteTreeNode};
+pub use migration::parse_config;
+#[cfg(test)]
+pub use paths::init_test_paths;
+pub use paths::{app_paths, exe_dir, migrate_from};
 pub use quick_actions_settings::QuickActionsSettings;
+pub use recovery::*;
 pub use settings::Settings;
+pub use store::ConfigStore;
+pub use synced_config::{SharedSettings, SyncedConfig};
 pub use utils::*;
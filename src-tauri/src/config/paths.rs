@@ -0,0 +1,244 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// 与 tauri.conf.json 中的 identifier 一致，作为各平台目录下的子目录名
+const IDENTIFIER: &str = "com.sworld.gsm";
+/// 可执行文件旁存在该文件时使用便携模式，所有文件都放在可执行文件所在目录下
+const PORTABLE_MARKER: &str = "GameSaveManager.portable";
+
+pub const CONFIG_FILE: &str = "GameSaveManager.config.json";
+pub const VAULT_FILE: &str = "GameSaveManager.vault";
+pub const DEVICE_FILE: &str = "GameSaveManager.device";
+pub const JOURNAL_FILE: &str = "GameSaveManager.journal.json";
+
+/// Where the app keeps its files
+///
+/// By default the platform directories are used (XDG base directories on Linux,
+/// `%APPDATA%` / `%LOCALAPPDATA%` on Windows). In portable mode everything stays
+/// next to the executable, as in earlier versions
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    pub portable: bool,
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    cache_dir: PathBuf,
+}
+
+impl AppPaths {
+    /// 所有目录都在 `root` 下，即便携模式的布局
+    pub fn portable<P: Into<PathBuf>>(root: P) -> Self {
+        let root = root.into();
+        AppPaths {
+            portable: true,
+            config_dir: root.clone(),
+            data_dir: root.clone(),
+            cache_dir: root,
+        }
+    }
+
+    fn detect() -> Self {
+        let root = exe_dir();
+        if root.join(PORTABLE_MARKER).exists() {
+            return Self::portable(root);
+        }
+        let platform_dirs = (|| {
+            use tauri::api::path::{cache_dir, config_dir, data_dir};
+            Some(AppPaths {
+                portable: false,
+                config_dir: config_dir()?.join(IDENTIFIER),
+                data_dir: data_dir()?.join(IDENTIFIER),
+                cache_dir: cache_dir()?.join(IDENTIFIER),
+            })
+        })();
+        // 无法确定平台目录时（如缺少 HOME）退回便携模式
+        platform_dirs.unwrap_or_else(|| Self::portable(root))
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join(CONFIG_FILE)
+    }
+
+    /// 配置文件旁的文件，如 `.bak`
+    pub fn config_sibling(&self, suffix: &str) -> PathBuf {
+        self.config_dir.join([CONFIG_FILE, suffix].concat())
+    }
+
+    pub fn vault_file(&self) -> PathBuf {
+        self.config_dir.join(VAULT_FILE)
+    }
+
+    pub fn device_file(&self) -> PathBuf {
+        self.config_dir.join(DEVICE_FILE)
+    }
+
+    pub fn journal_file(&self) -> PathBuf {
+        self.data_dir.join(JOURNAL_FILE)
+    }
+
+    pub fn log_dir(&self) -> PathBuf {
+        self.data_dir.join("log")
+    }
+
+    /// 恢复存档时解压的临时目录
+    pub fn tmp_dir(&self) -> PathBuf {
+        self.cache_dir.join("tmp")
+    }
+
+    /// 续传时缓存的待上传数据
+    pub fn upload_cache_dir(&self) -> PathBuf {
+        self.cache_dir.join("uploads")
+    }
+
+    /// 新建配置时使用的备份路径
+    pub fn default_backup_path(&self) -> PathBuf {
+        self.data_dir.join("save_data")
+    }
+
+    /// 创建配置、数据和缓存目录
+    pub fn create_dirs(&self) -> io::Result<()> {
+        for dir in [&self.config_dir, &self.data_dir, &self.cache_dir] {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+static PATHS: OnceLock<AppPaths> = OnceLock::new();
+
+/// 本次运行使用的目录，首次调用时确定
+pub fn app_paths() -> &'static AppPaths {
+    PATHS.get_or_init(AppPaths::detect)
+}
+
+/// 可执行文件所在目录，旧版本的文件和便携模式的文件都在这里
+pub fn exe_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// 测试使用临时目录，不读写真实的配置目录，需在首次调用 `app_paths` 前设置
+#[cfg(test)]
+pub fn init_test_paths() -> &'static AppPaths {
+    let root = std::env::temp_dir().join(format!("rgsm-test-{}", std::process::id()));
+    let paths = PATHS.get_or_init(|| AppPaths::portable(&root));
+    assert!(
+        paths.config_dir == root,
+        "app_paths() was used before init_test_paths()"
+    );
+    paths.create_dirs().unwrap();
+    paths
+}
+
+/// Move the files of an older version from `old_root` to the platform directories
+///
+/// Only happens once: when `paths` has no config yet and `old_root` has one.
+/// The config is moved last, so a migration that failed halfway is retried on
+/// the next start. A relative `backup_path` is made absolute so that existing
+/// snapshots stay where they are. Returns the files that were moved
+pub fn migrate_from(old_root: &Path, paths: &AppPaths) -> io::Result<Vec<PathBuf>> {
+    let old_config = old_root.join(CONFIG_FILE);
+    if paths.portable || paths.config_file().exists() || !old_config.exists() {
+        return Ok(Vec::new());
+    }
+    paths.create_dirs()?;
+    let old_root = old_root.canonicalize()?;
+
+    // 上次迁移中断时部分文件可能已经移动，目标已存在的跳过
+    let mut moved = Vec::new();
+    let vault_key = Path::new(VAULT_FILE).with_extension("key");
+    let files = [
+        (
+            PathBuf::from([CONFIG_FILE, ".bak"].concat()),
+            paths.config_sibling(".bak"),
+        ),
+        (PathBuf::from(VAULT_FILE), paths.vault_file()),
+        (vault_key.clone(), paths.config_dir.join(vault_key)),
+        (PathBuf::from(DEVICE_FILE), paths.device_file()),
+        (PathBuf::from(JOURNAL_FILE), paths.journal_file()),
+    ];
+    for (name, target) in files {
+        let source = old_root.join(name);
+        if source.exists() && !target.exists() {
+            move_file(&source, &target)?;
+            moved.push(target);
+        }
+    }
+
+    let mut config: serde_json::Value = serde_json::from_slice(&fs::read(&old_config)?)?;
+    if let Some(backup_path) = config.get_mut("backup_path") {
+        if let Some(relative) = backup_path.as_str().filter(|p| Path::new(p).is_relative()) {
+            let relative = relative.trim_start_matches("./");
+            *backup_path = old_root
+                .join(relative)
+                .to_string_lossy()
+                .into_owned()
+                .into();
+        }
+    }
+    // 配置文件最后写入，存在即表示迁移已完成
+    fs::write(paths.config_file(), serde_json::to_string_pretty(&config)?)?;
+    fs::remove_file(&old_config)?;
+    moved.push(paths.config_file());
+    Ok(moved)
+}
+
+/// 跨文件系统时无法直接重命名，改为复制后删除
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn migrate_portable_files() {
+        let root = std::env::temp_dir().join(format!("rgsm-migrate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let old_root = root.join("old");
+        fs::create_dir_all(&old_root).unwrap();
+        fs::write(
+            old_root.join(CONFIG_FILE),
+            r#"{"version":"1.4.0","backup_path":"./save_data"}"#,
+        )
+        .unwrap();
+        fs::write(old_root.join(DEVICE_FILE), "device").unwrap();
+        fs::write(old_root.join(VAULT_FILE), "vault").unwrap();
+
+        let paths = AppPaths {
+            portable: false,
+            config_dir: root.join("config"),
+            data_dir: root.join("data"),
+            cache_dir: root.join("cache"),
+        };
+        // 上次迁移在移动保险库后中断，配置文件还在旧目录中
+        paths.create_dirs().unwrap();
+        fs::write(paths.vault_file(), "moved vault").unwrap();
+        let moved = migrate_from(&old_root, &paths).unwrap();
+        assert_eq!(moved, vec![paths.device_file(), paths.config_file()]);
+        assert!(!old_root.join(CONFIG_FILE).exists());
+        assert_eq!(
+            fs::read_to_string(paths.vault_file()).unwrap(),
+            "moved vault"
+        );
+
+        // 相对的备份路径被改为旧目录下的绝对路径
+        let config: serde_json::Value =
+            serde_json::from_slice(&fs::read(paths.config_file()).unwrap()).unwrap();
+        let backup_path = PathBuf::from(config["backup_path"].as_str().unwrap());
+        assert!(backup_path.is_absolute());
+        assert!(backup_path.ends_with("old/save_data"));
+
+        // 只迁移一次
+        assert!(migrate_from(&old_root, &paths).unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
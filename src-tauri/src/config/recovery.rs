@@ -0,0 +1,130 @@
+use std::fs;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use super::paths::AppPaths;
+use super::utils::{config_bak_path, config_lock, init_config, read_config_file, write_atomic};
+use super::{app_paths, Config, ConfigStore};
+use crate::errors::ConfigError;
+
+/// 启动失败的原因，存在时处于安全模式
+static SAFE_MODE: OnceLock<String> = OnceLock::new();
+
+/// What the frontend needs to offer a way out of safe mode
+#[derive(Debug, Serialize, Clone)]
+pub struct StartupStatus {
+    /// 启动失败的原因，正常启动时为空
+    pub safe_mode_error: Option<String>,
+    /// `.bak` 中是否有可用的配置
+    pub backup_available: bool,
+}
+
+/// Start without a usable config file
+///
+/// The default config is served from memory and never written, so the broken
+/// file stays untouched until the user restores the backup or resets it.
+/// Quick actions and background sync are not started in safe mode
+pub fn enter_safe_mode(error: String) {
+    let _ = SAFE_MODE.set(error);
+    ConfigStore::global().replace(Config::default());
+}
+
+/// 处于安全模式时返回启动失败的原因
+pub fn safe_mode_error() -> Option<String> {
+    SAFE_MODE.get().cloned()
+}
+
+pub fn startup_status() -> StartupStatus {
+    StartupStatus {
+        safe_mode_error: safe_mode_error(),
+        backup_available: read_config_file(&config_bak_path()).is_ok(),
+    }
+}
+
+/// 无法使用的配置移动到 `.corrupt`，便于排查
+fn keep_broken_config(paths: &AppPaths) -> Result<(), ConfigError> {
+    if paths.config_file().exists() {
+        fs::rename(paths.config_file(), paths.config_sibling(".corrupt"))?;
+    }
+    Ok(())
+}
+
+/// Replace the config with the last working one, takes effect after a restart
+pub fn restore_config_backup() -> Result<(), ConfigError> {
+    let _guard = config_lock();
+    restore_backup_in(app_paths())
+}
+
+fn restore_backup_in(paths: &AppPaths) -> Result<(), ConfigError> {
+    let bak_path = paths.config_sibling(".bak");
+    // 备份也无法解析时保留原文件
+    read_config_file(&bak_path)?;
+    keep_broken_config(paths)?;
+    write_atomic(&paths.config_file(), &fs::read(bak_path)?)
+}
+
+/// Start over with a new config, takes effect after a restart
+///
+/// 游戏的存档不会被删除，可以重新添加游戏后找回
+pub fn reset_config_file() -> Result<(), ConfigError> {
+    let _guard = config_lock();
+    reset_in(app_paths())
+}
+
+fn reset_in(paths: &AppPaths) -> Result<(), ConfigError> {
+    keep_broken_config(paths)?;
+    init_config(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn broken_config(name: &str) -> AppPaths {
+        let root = std::env::temp_dir().join(format!("rgsm-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let paths = AppPaths::portable(root);
+        fs::write(paths.config_file(), "{ broken").unwrap();
+        paths
+    }
+
+    #[test]
+    fn restore_from_backup() {
+        let paths = broken_config("restore-config");
+        // 备份也无法使用时不改动原文件
+        assert!(restore_backup_in(&paths).is_err());
+        assert_eq!(fs::read_to_string(paths.config_file()).unwrap(), "{ broken");
+
+        let backup = serde_json::to_string(&Config {
+            backup_path: "/backups".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        fs::write(paths.config_sibling(".bak"), &backup).unwrap();
+        restore_backup_in(&paths).unwrap();
+        assert_eq!(fs::read_to_string(paths.config_file()).unwrap(), backup);
+        assert_eq!(
+            fs::read_to_string(paths.config_sibling(".corrupt")).unwrap(),
+            "{ broken"
+        );
+        fs::remove_dir_all(paths.config_file().parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn reset_keeps_broken_file() {
+        let paths = broken_config("reset-config");
+        reset_in(&paths).unwrap();
+        let config = read_config_file(&paths.config_file()).unwrap();
+        assert_eq!(
+            config.backup_path,
+            paths.default_backup_path().to_string_lossy()
+        );
+        assert_eq!(
+            fs::read_to_string(paths.config_sibling(".corrupt")).unwrap(),
+            "{ broken"
+        );
+        fs::remove_dir_all(paths.config_file().parent().unwrap()).unwrap();
+    }
+}
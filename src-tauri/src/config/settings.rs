@@ -31,6 +31,9 @@ pub struct Settings {
     pub log_to_file: bool,
     #[serde(default = "default_value::default_false")]
     pub add_new_to_favorites: bool,
+    /// 本地保留的存档数量，为0则全部保留
+    #[serde(default = "default_value::default_zero")]
+    pub local_retention: u64,
 }
 
 impl Sanitizable for Settings {
@@ -0,0 +1,151 @@
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock};
+
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use super::utils::read_config;
+use super::Config;
+use crate::errors::ConfigError;
+
+/// 配置变更时发送给前端的事件
+pub const CONFIG_CHANGED_EVENT: &str = "config-changed";
+
+type Listener = Box<dyn Fn(&Config, &Config) + Send + Sync>;
+
+/// The parsed config shared by the whole app
+///
+/// The config file is only read once, later reads are served from memory.
+/// Every write goes through `set_config` / `update_config`, which replace the
+/// cached config, notify the listeners and emit `config-changed` to the frontend
+pub struct ConfigStore {
+    config: RwLock<Option<Config>>,
+    app_handle: Mutex<Option<AppHandle>>,
+    listeners: Mutex<Vec<Listener>>,
+}
+
+impl ConfigStore {
+    /// 全局唯一的配置缓存，同时作为 Tauri 的 state 管理
+    pub fn global() -> Arc<ConfigStore> {
+        static STORE: OnceLock<Arc<ConfigStore>> = OnceLock::new();
+        STORE
+            .get_or_init(|| {
+                Arc::new(ConfigStore {
+                    config: RwLock::new(None),
+                    app_handle: Mutex::new(None),
+                    listeners: Mutex::new(Vec::new()),
+                })
+            })
+            .clone()
+    }
+
+    /// 获取缓存的配置，尚未缓存时从文件读取
+    pub fn get(&self) -> Result<Config, ConfigError> {
+        self.get_or_load(read_config)
+    }
+
+    /// 获取缓存的配置，尚未缓存时用 `load` 读取
+    pub(super) fn get_or_load<F>(&self, load: F) -> Result<Config, ConfigError>
+    where
+        F: FnOnce() -> Result<Config, ConfigError>,
+    {
+        if let Some(config) = self
+            .config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            return Ok(config.clone());
+        }
+        let config = load()?;
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = Some(config.clone());
+        Ok(config)
+    }
+
+    /// 配置文件写入后更新缓存，并通知监听者和前端
+    pub(super) fn replace(&self, config: Config) {
+        let old = self
+            .config
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(config.clone());
+        if let Some(old) = old {
+            for listener in self
+                .listeners
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+            {
+                listener(&old, &config);
+            }
+        }
+        if let Some(app_handle) = self
+            .app_handle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            if let Err(e) = app_handle.emit_all(CONFIG_CHANGED_EVENT, config) {
+                warn!(target:"rgsm::config","Cannot emit config change: {:?}", e);
+            }
+        }
+    }
+
+    /// 设置用于向前端发送事件的 handle，应在应用启动时调用
+    pub fn attach(&self, app_handle: AppHandle) {
+        *self
+            .app_handle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(app_handle);
+    }
+
+    /// 注册配置变更的回调，参数为变更前和变更后的配置
+    pub fn subscribe<F>(&self, listener: F)
+    where
+        F: Fn(&Config, &Config) + Send + Sync + 'static,
+    {
+        self.listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::new(listener));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn cached_config_and_listeners() {
+        let store = ConfigStore {
+            config: RwLock::new(None),
+            app_handle: Mutex::new(None),
+            listeners: Mutex::new(Vec::new()),
+        };
+        let loads = AtomicUsize::new(0);
+        let load = || {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok(Config::default())
+        };
+        store.get_or_load(load).unwrap();
+        store.get_or_load(load).unwrap();
+        // 只在第一次读取文件
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        let counter = changes.clone();
+        store.subscribe(move |old, new| {
+            assert_ne!(old.backup_path, new.backup_path);
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        store.replace(Config {
+            backup_path: "./other".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            store.get_or_load(load).unwrap().backup_path,
+            "./other".to_string()
+        );
+    }
+}
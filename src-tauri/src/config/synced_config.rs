@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::backup::Game;
+use crate::default_value;
+
+use super::{Config, FavoriteTreeNode, Settings};
+
+/// The part of the config that is shared between devices by cloud sync
+///
+/// Device-local fields (backup path, cloud backend and its credentials,
+/// hotkeys, locale, etc.) are never uploaded
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncedConfig {
+    pub version: String,
+    pub games: Vec<Game>,
+    #[serde(default = "default_value::empty_vec")]
+    pub favorites: Vec<FavoriteTreeNode>,
+    pub settings: SharedSettings,
+}
+
+/// Settings that are the same on every device
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedSettings {
+    #[serde(default = "default_value::default_true")]
+    pub prompt_when_not_described: bool,
+    #[serde(default = "default_value::default_true")]
+    pub extra_backup_when_apply: bool,
+    #[serde(default = "default_value::default_false")]
+    pub show_edit_button: bool,
+    #[serde(default = "default_value::default_true")]
+    pub prompt_when_auto_backup: bool,
+    #[serde(default = "default_value::default_false")]
+    pub default_delete_before_apply: bool,
+    #[serde(default = "default_value::default_false")]
+    pub default_expend_favorites_tree: bool,
+    #[serde(default = "default_value::default_false")]
+    pub add_new_to_favorites: bool,
+}
+
+impl From<&Config> for SyncedConfig {
+    fn from(config: &Config) -> Self {
+        SyncedConfig {
+            version: config.version.clone(),
+            games: config.games.clone(),
+            favorites: config.favorites.clone(),
+            settings: SharedSettings::from(&config.settings),
+        }
+    }
+}
+
+impl From<&Settings> for SharedSettings {
+    fn from(settings: &Settings) -> Self {
+        SharedSettings {
+            prompt_when_not_described: settings.prompt_when_not_described,
+            extra_backup_when_apply: settings.extra_backup_when_apply,
+            show_edit_button: settings.show_edit_button,
+            prompt_when_auto_backup: settings.prompt_when_auto_backup,
+            default_delete_before_apply: settings.default_delete_before_apply,
+            default_expend_favorites_tree: settings.default_expend_favorites_tree,
+            add_new_to_favorites: settings.add_new_to_favorites,
+        }
+    }
+}
+
+impl Config {
+    /// Merge the synced part downloaded from the cloud,
+    /// device-local fields are kept untouched
+    pub fn apply_synced(&mut self, synced: SyncedConfig) {
+        self.games = synced.games;
+        self.favorites = synced.favorites;
+        self.settings.apply_shared(synced.settings);
+    }
+}
+
+impl Settings {
+    pub fn apply_shared(&mut self, shared: SharedSettings) {
+        self.prompt_when_not_described = shared.prompt_when_not_described;
+        self.extra_backup_when_apply = shared.extra_backup_when_apply;
+        self.show_edit_button = shared.show_edit_button;
+        self.prompt_when_auto_backup = shared.prompt_when_auto_backup;
+        self.default_delete_before_apply = shared.default_delete_before_apply;
+        self.default_expend_favorites_tree = shared.default_expend_favorites_tree;
+        self.add_new_to_favorites = shared.add_new_to_favorites;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cloud_sync::Backend;
+
+    #[test]
+    fn legacy_full_config_keeps_local_credentials() {
+        // 旧版本会将完整配置上传到云端
+        let mut remote = Config {
+            backup_path: "D:/remote_saves".to_string(),
+            ..Default::default()
+        };
+        remote.settings.show_edit_button = true;
+        remote.settings.cloud_settings.backend = Backend::WebDAV {
+            endpoint: "https://remote".to_string(),
+            username: "remote".to_string(),
+            password: "remote".to_string(),
+        };
+        let legacy = serde_json::to_string(&remote).unwrap();
+        let synced: SyncedConfig = serde_json::from_str(&legacy).unwrap();
+        assert!(!serde_json::to_string(&synced).unwrap().contains("password"));
+
+        let mut local = Config::default();
+        local.settings.cloud_settings.backend = Backend::Disabled;
+        local.apply_synced(synced);
+        assert!(local.settings.show_edit_button);
+        assert_eq!(local.backup_path, Config::default().backup_path);
+        assert!(matches!(
+            local.settings.cloud_settings.backend,
+            Backend::Disabled
+        ));
+    }
+}
@@ -1,104 +1,292 @@
+use std::fs;
 use std::fs::File;
-use std::{fs, path};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
 
 use rust_i18n::t;
 use semver::Version;
+use serde_json::Value;
 use tauri::api::notification::Notification;
-use tracing::info;
+use tracing::{error, info, warn};
 
-use super::Config;
+use super::migration::{migrate_config, MigrationOutcome};
+use super::paths::AppPaths;
+use super::recovery::safe_mode_error;
+use super::{app_paths, Config, ConfigStore};
+use crate::cloud_sync::{sync_later, PendingOperation};
 use crate::errors::ConfigError;
 
+/// 配置文件的路径
+pub(super) fn config_path() -> PathBuf {
+    app_paths().config_file()
+}
+
+/// 上一份可用的配置，配置文件损坏时从这里恢复
+pub(super) fn config_bak_path() -> PathBuf {
+    app_paths().config_sibling(".bak")
+}
+
+/// 无法解析的配置文件会被移动到这里，便于排查
+pub(super) fn config_corrupt_path() -> PathBuf {
+    app_paths().config_sibling(".corrupt")
+}
+
+/// 保护配置文件的读-改-写，同一时间只有一个操作能修改配置
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+/// 配置版本高于软件时的版本号，此时不会写入配置
+static READ_ONLY_VERSION: OnceLock<String> = OnceLock::new();
+
+pub(super) fn config_lock() -> MutexGuard<'static, ()> {
+    CONFIG_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 /// Set settings to original state
 pub async fn reset_settings() -> Result<(), ConfigError> {
     let settings = Config::default().settings;
-    let mut config = get_config()?;
-    config.settings = settings;
-    set_config(&config).await
+    update_config(|config| {
+        config.settings = settings;
+        Ok::<(), ConfigError>(())
+    })
+    .await
 }
 
 /// Create a config file
-fn init_config() -> Result<(), ConfigError> {
+pub(super) fn init_config(paths: &AppPaths) -> Result<(), ConfigError> {
     info!("Init config file.");
-    fs::write(
-        "./GameSaveManager.config.json",
-        serde_json::to_string_pretty(&Config::default())?,
+    // 新配置的备份路径放在数据目录下，便携模式下仍为 `./save_data`
+    let config = Config {
+        backup_path: paths.default_backup_path().to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    write_atomic(
+        &paths.config_file(),
+        serde_json::to_string_pretty(&config)?.as_bytes(),
     )?;
     Ok(())
 }
 
-/// Get the current config file
+/// 先写入同目录下的临时文件并落盘，再重命名为目标文件，
+/// 避免写入一半时崩溃导致文件损坏，重命名在同一目录下是原子的
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), ConfigError> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp_path, path)?;
+    // 确保重命名本身也已落盘，只有 unix 支持打开目录
+    #[cfg(unix)]
+    {
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+pub(super) fn read_config_file(path: &Path) -> Result<Config, ConfigError> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Get the current config
+///
+/// The file is only read the first time, see `ConfigStore`
 pub fn get_config() -> Result<Config, ConfigError> {
-    let file = File::open("./GameSaveManager.config.json")?;
-    Ok(serde_json::from_reader(file)?)
+    ConfigStore::global().get()
 }
 
-/// Replace the config file with a new config struct
-pub async fn set_config(config: &Config) -> Result<(), ConfigError> {
-    fs::write(
-        "./GameSaveManager.config.json",
-        serde_json::to_string_pretty(&config)?,
+/// 从文件读取配置，无法解析时从 `.bak` 中的上一份可用配置恢复
+pub(super) fn read_config() -> Result<Config, ConfigError> {
+    match read_config_file(&config_path()) {
+        Err(ConfigError::Deserialize(_)) => {
+            let _guard = config_lock();
+            // 等待锁期间其他操作可能已经恢复了配置
+            load_config()
+        }
+        result => result,
+    }
+}
+
+/// 读取配置，损坏时从备份恢复，调用者需持有配置锁
+fn load_config() -> Result<Config, ConfigError> {
+    match read_config_file(&config_path()) {
+        // 更新版本的配置无法解析时不能视为损坏
+        Err(ConfigError::Deserialize(e)) if read_only_version().is_none() => {
+            error!(target:"rgsm::config","Config file is corrupted: {:?}", e);
+            let config = read_config_file(&config_bak_path()).map_err(|bak_err| {
+                error!(target:"rgsm::config","Cannot recover config from backup: {:?}", bak_err);
+                ConfigError::Deserialize(e)
+            })?;
+            warn!(target:"rgsm::config","Restoring config from {:?}", config_bak_path());
+            fs::rename(config_path(), config_corrupt_path())?;
+            write_atomic(&config_path(), &fs::read(config_bak_path())?)?;
+            Ok(config)
+        }
+        result => result,
+    }
+}
+
+/// 保存配置，调用者需持有配置锁
+fn write_config(config: &Config) -> Result<Config, ConfigError> {
+    if let Some(version) = read_only_version() {
+        return Err(ConfigError::ReadOnly(version));
+    }
+    if safe_mode_error().is_some() {
+        return Err(ConfigError::SafeMode);
+    }
+    let mut config = config.clone();
+    // 根据设置将凭据移入或移出安全存储
+    config.settings.cloud_settings.apply_secret_storage()?;
+    // 覆盖前保留当前可用的配置，用于损坏时恢复
+    if read_config_file(&config_path()).is_ok() {
+        fs::copy(config_path(), config_bak_path())?;
+    }
+    write_atomic(
+        &config_path(),
+        serde_json::to_string_pretty(&config)?.as_bytes(),
     )?;
-    // 处理云同步，上传新的配置文件
+    ConfigStore::global().replace(config.clone());
+    Ok(config)
+}
+
+/// 处理云同步，上传新的配置文件，失败时稍后重试
+async fn sync_config(config: &Config) {
     if config.settings.cloud_settings.always_sync {
-        let op = config.settings.cloud_settings.backend.get_op()?;
-        crate::cloud_sync::upload_config(&op).await?;
+        sync_later(PendingOperation::UploadConfig);
     }
+}
+
+/// Replace the config file with a new config struct
+pub async fn set_config(config: &Config) -> Result<(), ConfigError> {
+    let config = {
+        let _guard = config_lock();
+        write_config(config)?
+    };
+    sync_config(&config).await;
     Ok(())
 }
 
+/// Modify the config while holding the config lock, then save it
+///
+/// Use this instead of `get_config` followed by `set_config`,
+/// so that concurrent updates are not lost
+pub async fn update_config<T, E, F>(f: F) -> Result<T, E>
+where
+    F: FnOnce(&mut Config) -> Result<T, E>,
+    E: From<ConfigError>,
+{
+    let (result, config) = {
+        let _guard = config_lock();
+        let mut config = ConfigStore::global().get_or_load(load_config)?;
+        let result = f(&mut config)?;
+        (result, write_config(&config)?)
+    };
+    sync_config(&config).await;
+    Ok(result)
+}
+
 /// Check the config file exists or not
 /// if not, then create one
-/// then send the config to the front end
+/// then migrate it to the current version
 pub fn config_check() -> Result<(), ConfigError> {
-    let config_path = path::Path::new("./GameSaveManager.config.json");
+    let config_path = config_path();
     if !config_path.is_file() || !config_path.exists() {
-        init_config()?;
+        init_config(app_paths())?;
     }
-    let mut config = get_config()?;
+    // 配置损坏时返回错误，由用户选择从备份恢复或重置
+    let mut raw: Value = serde_json::from_slice(&fs::read(&config_path)?)?;
 
-    // 处理早期版本兼容性
-    if config.version == "1.0.0 alpha" {
-        "1.0.0-alpha".clone_into(&mut config.version);
-    }
     let software_version = Version::parse(&Config::default().version)?;
-    let config_version = Version::parse(&config.version)?;
-    if config_version != software_version {
-        Notification::new("Update Config Info")
-            .title(t!("backend.config.updating_config_title"))
-            .body(t!("backend.config.updating_config_body"))
-            .show()
-            .expect("Cannot show notification");
-        backup_old_config()?;
-    }
-    if config_version < Version::parse("1.0.0")? {
-        panic!("The config version is not supported.It's too old.")
-    }
-    if config_version < software_version {
-        upgrade_config_version(&mut config, &software_version)?;
-    }
-    if config_version > software_version {
-        panic!("The config version is higher than the software.")
+    match migrate_config(&mut raw, &software_version)? {
+        MigrationOutcome::UpToDate => {
+            // 检查配置能否解析，避免之后再出错
+            serde_json::from_value::<Config>(raw)?;
+        }
+        MigrationOutcome::Migrated { from } => {
+            info!(target:"rgsm::config","Migrating config from {} to {}", from, software_version);
+            let config: Config = serde_json::from_value(raw)?;
+            show_notification(
+                t!("backend.config.updating_config_title"),
+                t!("backend.config.updating_config_body"),
+            );
+            backup_old_config(&from)?;
+            tauri::async_runtime::block_on(set_config(&config))?;
+        }
+        MigrationOutcome::Newer { version } => {
+            // 不认识的字段会在保存时丢失，因此不允许修改配置
+            warn!(target:"rgsm::config","Config version {} is newer than the software, read-only mode", version);
+            let _ = READ_ONLY_VERSION.set(version.to_string());
+            show_notification(
+                t!("backend.config.read_only_title"),
+                t!("backend.config.read_only_body"),
+            );
+        }
     }
 
+    let config = get_config()?;
     rust_i18n::set_locale(&config.settings.locale);
-    Ok(()) // return the config json
+    Ok(())
 }
 
-fn upgrade_config_version(
-    config: &mut Config,
-    software_version: &semver::Version,
-) -> Result<(), ConfigError> {
-    // 由于1.0之后版本保持了兼容性，因此不需要做任何处理，仅更新版本号并保存
-    config.version = software_version.to_string();
-    tauri::async_runtime::block_on(set_config(config))?;
-    Ok(())
+fn show_notification<T1: Into<String>, T2: Into<String>>(title: T1, body: T2) {
+    if let Err(e) = Notification::new("Update Config Info")
+        .title(title)
+        .body(body)
+        .show()
+    {
+        warn!(target:"rgsm::config","Cannot show notification: {:?}", e);
+    }
 }
 
-fn backup_old_config() -> Result<(), ConfigError> {
+/// 迁移前的配置保留为 `.<版本>.bak`，不会被之后的保存覆盖
+fn backup_old_config(version: &Version) -> Result<(), ConfigError> {
     fs::copy(
-        "./GameSaveManager.config.json",
-        "./GameSaveManager.config.json.bak",
+        config_path(),
+        app_paths().config_sibling(&format!(".{}.bak", version)),
     )?;
     Ok(())
 }
+
+/// 配置由更新版本的软件写入时返回其版本号，此时配置是只读的
+pub fn read_only_version() -> Option<String> {
+    READ_ONLY_VERSION.get().cloned()
+}
+
+/// 写入测试用的配置，备份路径在测试目录下，只写入一次
+#[cfg(test)]
+pub fn init_test_config() {
+    super::init_test_paths();
+    static CONFIG: OnceLock<()> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let config = Config {
+            backup_path: app_paths()
+                .default_backup_path()
+                .to_string_lossy()
+                .into_owned(),
+            ..Default::default()
+        };
+        fs::write(config_path(), serde_json::to_string(&config).unwrap()).unwrap();
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn atomic_write_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("rgsm-atomic-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, "old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        // 临时文件在重命名后不再存在
+        assert!(!dir.join("config.json.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
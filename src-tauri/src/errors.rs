@@ -40,6 +40,26 @@ pub enum BackendError {
     ReadCloudInfo(#[from] FromUtf8Error),
     #[error("Deserialize error: {0:#?}")]
     Deserialize(#[from] serde_json::Error),
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    #[error("Cloud file is encrypted but no passphrase is set")]
+    MissingPassphrase,
+    #[error("Cannot decrypt cloud file, the passphrase may be wrong")]
+    WrongPassphrase,
+    #[error("Cloud file is corrupted")]
+    CorruptedCloudFile,
+    #[error("Secret store error: {0}")]
+    SecretStore(String),
+    #[error("Credential {0} not found in secret store")]
+    SecretNotFound(String),
+    #[error("Cloud job cancelled")]
+    Cancelled,
+    #[error("Cloud storage is being synced by device {device}, the lock expires at {expires_at}")]
+    Locked { device: String, expires_at: String },
+    #[error("Game {0} is not found in the cloud")]
+    RemoteGameNotFound(String),
+    #[error("Game {0} already exists locally")]
+    GameAlreadyExists(String),
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
 }
@@ -65,10 +85,16 @@ impl From<BackupError> for BackendError {
 /// 备份或恢复快照时可能产生的错误
 #[derive(Debug, Error)]
 pub enum BackupError {
-    #[error("Backup for {name} not exists: {date}")]
-    BackupNotExist { name: String, date: String },
+    #[error("Backup for {name} not exists: {id}")]
+    BackupNotExist { name: String, id: String },
+    #[error("Branch of {name} not exists: {id}")]
+    BranchNotExist { name: String, id: String },
+    #[error("Backups.json of {name} is written by a newer version ({version}), it is read-only")]
+    NewerSnapshotsInfo { name: String, version: u32 },
     #[error("No backups available")]
     NoBackupAvailable,
+    #[error("Backup for {name} is not in the cloud: {id}")]
+    NotInCloud { name: String, id: String },
     #[error("Backend error: {0:#?}")]
     Backend(#[from] BackendError),
     #[error("Compress/Decompress error: {0:#?}")]
@@ -81,6 +107,18 @@ pub enum BackupError {
     Io(#[from] io::Error),
     #[error("Cannot create extra backup")]
     ExtraBackupFailed,
+    #[error("Cannot read or write archive: {0:#?}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Not a valid exported file: {0}")]
+    InvalidArchive(String),
+    #[error("Game already exists: {0}")]
+    GameExists(String),
+    #[error("Game not found: {0}")]
+    GameNotFound(String),
+    #[error("Profile of {name} not exists: {profile}")]
+    ProfileNotExist { name: String, profile: String },
+    #[error("Profile of {name} already exists or is invalid: {profile}")]
+    ProfileExists { name: String, profile: String },
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
 }
@@ -111,4 +149,10 @@ pub enum ConfigError {
     Tauri(#[from] tauri::Error),
     #[error("Semver error: {0:#?}")]
     Semver(#[from] semver::Error),
+    #[error("Unsupported config version: {0}")]
+    UnsupportedVersion(String),
+    #[error("The config was written by a newer version ({0}) and is read-only")]
+    ReadOnly(String),
+    #[error("Started in safe mode, the config will not be saved")]
+    SafeMode,
 }
@@ -1,6 +1,9 @@
-use crate::backup::{Game, GameSnapshots};
-use crate::cloud_sync::{self, upload_all, Backend};
-use crate::config::{get_config, Config};
+use crate::backup::{Game, GameSnapshots, SnapshotTrigger};
+use crate::cloud_sync::{
+    self, upload_all, Backend, CloudJobs, JournalEntry, PrunedSnapshot, RemoteGame,
+    TransferDirection, TransferJob,
+};
+use crate::config::{get_config, Config, ConfigStore};
 use crate::errors::*;
 use crate::traits::Sanitizable;
 use crate::{backup, config, quick_actions};
@@ -9,8 +12,9 @@ use rust_embed::Embed;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::api::dialog;
-use tauri::{AppHandle, Manager, Window};
+use tauri::{AppHandle, Manager, State, Window};
 use tracing::{debug, error, info, warn};
 
 #[allow(non_camel_case_types)]
@@ -33,6 +37,15 @@ pub struct IpcNotification {
 #[prefix = "locales/"]
 struct Asset;
 
+/// 安全模式下使用的默认配置没有可用的备份路径，拒绝读写存档的操作
+fn reject_in_safe_mode() -> Result<(), String> {
+    if config::safe_mode_error().is_some() {
+        warn!(target:"rgsm::ipc", "Rejected in safe mode");
+        return Err(ConfigError::SafeMode.to_string());
+    }
+    Ok(())
+}
+
 #[allow(unused)]
 #[tauri::command]
 pub async fn open_url(url: String) -> Result<(), String> {
@@ -43,6 +56,28 @@ pub async fn open_url(url: String) -> Result<(), String> {
     })
 }
 
+#[allow(unused)]
+#[tauri::command]
+pub async fn launch_game(game: Game) -> Result<(), String> {
+    info!(target:"rgsm::ipc", "Launching game: {}", game.name);
+    let Some(path) = game.game_path.clone() else {
+        return Err("No launch path".to_string());
+    };
+    // 启动前备份一次，备份失败不阻止启动；安全模式下没有可用的备份路径
+    if config::safe_mode_error().is_none() {
+        if let Err(e) = game
+            .create_snapshot("Before launch", SnapshotTrigger::Launcher)
+            .await
+        {
+            warn!(target:"rgsm::ipc", "Failed to back up {} before launch: {:?}", game.name, e);
+        }
+    }
+    open::that(path).map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to launch game: {:?}", e);
+        e.to_string()
+    })
+}
+
 #[allow(unused)]
 #[tauri::command]
 pub async fn choose_save_file() -> Result<String, String> {
@@ -71,14 +106,53 @@ pub async fn choose_save_dir() -> Result<String, String> {
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn get_local_config() -> Result<Config, String> {
+pub async fn get_local_config(store: State<'_, Arc<ConfigStore>>) -> Result<Config, String> {
     info!(target:"rgsm::ipc", "Getting local config.");
-    get_config().map_err(|e| e.to_string())
+    store.get().map_err(|e| e.to_string())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn get_config_read_only() -> Result<Option<String>, String> {
+    info!(target:"rgsm::ipc", "Checking whether the config is read-only.");
+    Ok(config::read_only_version())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn get_startup_status() -> Result<config::StartupStatus, String> {
+    info!(target:"rgsm::ipc", "Getting startup status.");
+    Ok(config::startup_status())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn restore_config_backup(app_handle: AppHandle) -> Result<(), String> {
+    info!(target:"rgsm::ipc", "Restoring config from backup.");
+    config::restore_config_backup().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to restore config: {:?}", e);
+        e.to_string()
+    })?;
+    app_handle.restart();
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn reset_config_file(app_handle: AppHandle) -> Result<(), String> {
+    info!(target:"rgsm::ipc", "Resetting config file.");
+    config::reset_config_file().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to reset config: {:?}", e);
+        e.to_string()
+    })?;
+    app_handle.restart();
+    Ok(())
 }
 
 #[allow(unused)]
 #[tauri::command]
 pub async fn add_game(game: Game) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc", "Adding game: {:?}", game);
     backup::create_game_backup(&game).await.map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to add game: {:?}", e);
@@ -90,14 +164,12 @@ pub async fn add_game(game: Game) -> Result<(), String> {
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn restore_snapshot(
-    game: Game,
-    date: String,
-    app_handle: AppHandle,
-) -> Result<(), String> {
+pub async fn restore_snapshot(game: Game, id: String, app_handle: AppHandle) -> Result<(), String> {
+    reject_in_safe_mode()?;
     //handle_backup_err(game.restore_snapshot(&date,window), )
-    info!(target:"rgsm::ipc", "Applying backup: {:?} for game: {:?}", date, game);
-    game.restore_snapshot(&date, Some(&app_handle))
+    info!(target:"rgsm::ipc", "Applying backup: {:?} for game: {:?}", id, game);
+    game.restore_snapshot(&id, Some(&app_handle))
+        .await
         .map_err(|e| {
             match &e {
                 BackupError::ExtraBackupFailed => {
@@ -116,25 +188,40 @@ pub async fn restore_snapshot(
             }
             e.to_string()
         })?;
-    info!(target:"rgsm::ipc", "Successfully applied backup: {:?} for game: {:?}", date, game);
+    info!(target:"rgsm::ipc", "Successfully applied backup: {:?} for game: {:?}", id, game);
     Ok(())
 }
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn delete_snapshot(game: Game, date: String) -> Result<(), String> {
-    info!(target:"rgsm::ipc", "Deleting backup: {:?} for game: {:?}", date, game);
-    game.delete_snapshot(&date).await.map_err(|e| {
+pub async fn delete_snapshot(game: Game, id: String) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Deleting backup: {:?} for game: {:?}", id, game);
+    game.delete_snapshot(&id).await.map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to delete backup: {:?}", e);
         e.to_string()
     })?;
-    info!(target:"rgsm::ipc", "Successfully deleted backup: {:?} for game: {:?}", date, game);
+    info!(target:"rgsm::ipc", "Successfully deleted backup: {:?} for game: {:?}", id, game);
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn evict_local_snapshot(game: Game, id: String) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Evicting local copy of backup: {:?} for game: {:?}", id, game);
+    game.evict_snapshot(&id).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to evict local copy of backup: {:?}", e);
+        e.to_string()
+    })?;
+    info!(target:"rgsm::ipc", "Successfully evicted local copy of backup: {:?} for game: {:?}", id, game);
     Ok(())
 }
 
 #[allow(unused)]
 #[tauri::command]
 pub async fn delete_game(game: Game) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc", "Deleting game: {:?}", game);
     game.delete_game().await.map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to delete game: {:?}", e);
@@ -176,9 +263,16 @@ pub async fn reset_settings() -> Result<(), String> {
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn create_snapshot(game: Game, describe: String, window: Window) -> Result<(), String> {
+pub async fn create_snapshot(
+    game: Game,
+    describe: String,
+    trigger: Option<SnapshotTrigger>,
+    window: Window,
+) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc", "Backing up save for game: {:?}", game);
-    handle_backup_err(game.create_snapshot(&describe).await, window)?;
+    let trigger = trigger.unwrap_or(SnapshotTrigger::Manual);
+    handle_backup_err(game.create_snapshot(&describe, trigger).await, window)?;
     info!(target:"rgsm::ipc", "Successfully backed up save for game: {:?}", game);
     Ok(())
 }
@@ -213,13 +307,22 @@ pub async fn check_cloud_backend(backend: Backend) -> Result<(), String> {
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn cloud_upload_all(backend: Backend) -> Result<(), String> {
+pub async fn cloud_upload_all(
+    backend: Backend,
+    app_handle: AppHandle,
+    jobs: State<'_, CloudJobs>,
+) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc", "Uploading all backups to cloud backend: {:?}", backend.clone().sanitize());
     let op = backend.get_op().map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
         e.to_string()
     })?;
-    match upload_all(&op).await {
+    let job = TransferJob::new(TransferDirection::Upload, Some(app_handle));
+    jobs.register(&job);
+    let result = upload_all(&op, &job).await;
+    jobs.remove(&job.id());
+    match result {
         Ok(_) => {
             info!(target:"rgsm::ipc", "Successfully uploaded all backups to cloud backend: {:?}", backend.sanitize());
             Ok(())
@@ -233,13 +336,22 @@ pub async fn cloud_upload_all(backend: Backend) -> Result<(), String> {
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn cloud_download_all(backend: Backend) -> Result<(), String> {
+pub async fn cloud_download_all(
+    backend: Backend,
+    app_handle: AppHandle,
+    jobs: State<'_, CloudJobs>,
+) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc", "Downloading all backups from cloud backend: {:?}", backend.clone().sanitize());
     let op = backend.get_op().map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
         e.to_string()
     })?;
-    match cloud_sync::download_all(&op).await {
+    let job = TransferJob::new(TransferDirection::Download, Some(app_handle));
+    jobs.register(&job);
+    let result = cloud_sync::download_all(&op, &job).await;
+    jobs.remove(&job.id());
+    match result {
         Ok(_) => {
             info!(target:"rgsm::ipc", "Successfully downloaded all backups from cloud backend: {:?}", backend.sanitize());
             Ok(())
@@ -251,27 +363,270 @@ pub async fn cloud_download_all(backend: Backend) -> Result<(), String> {
     }
 }
 
+#[allow(unused)]
+#[tauri::command]
+pub async fn cloud_list_remote(backend: Backend) -> Result<Vec<RemoteGame>, String> {
+    info!(target:"rgsm::ipc", "Listing remote games in cloud backend: {:?}", backend.clone().sanitize());
+    let op = backend.get_op().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
+        e.to_string()
+    })?;
+    cloud_sync::list_remote(&op).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to list remote games: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn cloud_delete_remote_snapshot(
+    backend: Backend,
+    game: String,
+    id: String,
+) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Deleting remote backup: {:?} for game: {:?}", id, game);
+    let op = backend.get_op().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
+        e.to_string()
+    })?;
+    cloud_sync::delete_remote_snapshot(&op, &game, &id)
+        .await
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to delete remote backup: {:?}", e);
+            e.to_string()
+        })?;
+    info!(target:"rgsm::ipc", "Successfully deleted remote backup: {:?} for game: {:?}", id, game);
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn cloud_download_game(
+    backend: Backend,
+    game: Game,
+    app_handle: AppHandle,
+    jobs: State<'_, CloudJobs>,
+) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Downloading backups of game: {:?}", game.name);
+    let op = backend.get_op().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
+        e.to_string()
+    })?;
+    let job = TransferJob::new(TransferDirection::Download, Some(app_handle));
+    jobs.register(&job);
+    let result = cloud_sync::download_game(&op, &game, &job).await;
+    jobs.remove(&job.id());
+    result.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to download backups of game: {:?}", e);
+        e.to_string()
+    })?;
+    info!(target:"rgsm::ipc", "Successfully downloaded backups of game: {:?}", game.name);
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn cloud_import_game(backend: Backend, name: String) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Importing remote game: {:?}", name);
+    let op = backend.get_op().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
+        e.to_string()
+    })?;
+    cloud_sync::import_remote_game(&op, &name)
+        .await
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to import remote game: {:?}", e);
+            e.to_string()
+        })?;
+    info!(target:"rgsm::ipc", "Successfully imported remote game: {:?}", name);
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn cloud_prune(backend: Backend) -> Result<Vec<PrunedSnapshot>, String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Pruning remote snapshots");
+    let op = backend.get_op().map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to get cloud backend operator: {:?}", e);
+        e.to_string()
+    })?;
+    let pruned = cloud_sync::prune_remote(&op, None).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to prune remote snapshots: {:?}", e);
+        e.to_string()
+    })?;
+    info!(target:"rgsm::ipc", "Pruned {} remote snapshots", pruned.len());
+    Ok(pruned)
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn get_sync_queue() -> Result<Vec<JournalEntry>, String> {
+    Ok(cloud_sync::pending_operations())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn retry_sync_queue() -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Replaying queued cloud operations");
+    cloud_sync::replay_journal().await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to replay queued cloud operations: {:?}", e);
+        e.to_string()
+    })?;
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn cancel_cloud_job(job_id: String, jobs: State<'_, CloudJobs>) -> Result<(), String> {
+    info!(target:"rgsm::ipc", "Cancelling cloud job: {}", job_id);
+    if jobs.cancel(&job_id) {
+        Ok(())
+    } else {
+        warn!(target:"rgsm::ipc", "Cloud job not found: {}", job_id);
+        Err(format!("Cloud job {} not found", job_id))
+    }
+}
+
 #[allow(unused)]
 #[tauri::command]
 pub async fn set_snapshot_description(
     game: Game,
-    date: String,
+    id: String,
     describe: String,
 ) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc", "Setting backup describe for game: {:?}", game);
-    game.set_snapshot_description(&date, &describe)
+    game.set_snapshot_description(&id, &describe)
         .await
         .map_err(|e| {
             error!(target:"rgsm::ipc", "Failed to set backup describe: {:?}", e);
             e.to_string()
         })?;
-    info!(target:"rgsm::ipc", "Successfully set backup {} describe for game: {:?}", date,game);
+    info!(target:"rgsm::ipc", "Successfully set backup {} describe for game: {:?}", id,game);
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn set_snapshot_pinned(game: Game, id: String, pinned: bool) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Setting backup {} pinned to {} for game: {:?}", id, pinned, game.name);
+    game.set_snapshot_pinned(&id, pinned).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to set backup pinned: {:?}", e);
+        e.to_string()
+    })?;
+    Ok(())
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn add_snapshot_tags(game: Game, id: String, tags: Vec<String>) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Adding tags {:?} to backup {} for game: {:?}", tags, id, game.name);
+    game.add_snapshot_tags(&id, &tags).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to add backup tags: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn remove_snapshot_tags(game: Game, id: String, tags: Vec<String>) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Removing tags {:?} from backup {} for game: {:?}", tags, id, game.name);
+    game.remove_snapshot_tags(&id, &tags).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to remove backup tags: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn get_snapshot_branches(game: Game) -> Result<Vec<backup::SnapshotBranch>, String> {
+    info!(target:"rgsm::ipc", "Getting backup branches for game: {:?}", game.name);
+    game.get_game_snapshots_info()
+        .map(|info| info.branches())
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to get backup branches: {:?}", e);
+            e.to_string()
+        })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn get_branch_snapshots(
+    game: Game,
+    branch: String,
+) -> Result<Vec<backup::Snapshot>, String> {
+    info!(target:"rgsm::ipc", "Getting backups of branch {} for game: {:?}", branch, game.name);
+    game.get_branch_snapshots(&branch).map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to get branch backups: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn set_branch_name(game: Game, branch: String, name: String) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Setting name of branch {} to {:?} for game: {:?}", branch, name, game.name);
+    game.set_branch_name(&branch, &name).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to set branch name: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn create_profile(game: Game, profile: String) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Creating profile {} for game: {:?}", profile, game.name);
+    game.create_profile(&profile).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to create profile: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn switch_profile(
+    game: Game,
+    profile: Option<String>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc", "Switching to profile {:?} for game: {:?}", profile, game.name);
+    game.switch_profile(profile.as_deref(), Some(&app_handle))
+        .await
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to switch profile: {:?}", e);
+            e.to_string()
+        })?;
+    info!(target:"rgsm::ipc", "Successfully switched profile for game: {:?}", game.name);
     Ok(())
 }
 
+#[allow(unused)]
+#[tauri::command]
+pub async fn search_snapshots(
+    query: backup::SnapshotQuery,
+) -> Result<Vec<backup::SnapshotSearchResult>, String> {
+    info!(target:"rgsm::ipc", "Searching backups: {:?}", query);
+    backup::search_snapshots(&query).map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to search backups: {:?}", e);
+        e.to_string()
+    })
+}
+
 #[allow(unused)]
 #[tauri::command]
 pub async fn backup_all() -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc","Backing up all games.");
     backup::backup_all().await.map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to backup all games: {:?}", e);
@@ -284,6 +639,7 @@ pub async fn backup_all() -> Result<(), String> {
 #[allow(unused)]
 #[tauri::command]
 pub async fn apply_all(app_handle: AppHandle) -> Result<(), String> {
+    reject_in_safe_mode()?;
     info!(target:"rgsm::ipc","Applying all backups.");
     backup::apply_all(Some(&app_handle)).await.map_err(|e| {
         error!(target:"rgsm::ipc", "Failed to apply all backups: {:?}", e);
@@ -295,10 +651,123 @@ pub async fn apply_all(app_handle: AppHandle) -> Result<(), String> {
 
 #[allow(unused)]
 #[tauri::command]
-pub async fn set_quick_backup_game(app_handle: AppHandle, game: Game) -> Result<(), String> {
+pub async fn set_quick_backup_game(game: Game) -> Result<(), String> {
     info!(target:"rgsm::ipc","Setting quick backup game to: {:?}", game);
-    quick_actions::set_current_game(&app_handle, game).await;
-    Ok(())
+    quick_actions::set_current_game(game).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to set quick backup game: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn export_library(include_credentials: bool) -> Result<Option<String>, String> {
+    info!(target:"rgsm::ipc","Exporting library, include credentials: {}", include_credentials);
+    let file_name = chrono::Local::now()
+        .format("RGSM_Library_%Y-%m-%d.zip")
+        .to_string();
+    let Some(path) = dialog::blocking::FileDialogBuilder::new()
+        .add_filter("RGSM Library", &["zip"])
+        .set_file_name(&file_name)
+        .save_file()
+    else {
+        warn!(target:"rgsm::ipc", "User closed the dialog, export cancelled.");
+        return Ok(None);
+    };
+    backup::export_library(&path, include_credentials).map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to export library: {:?}", e);
+        e.to_string()
+    })?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn import_library(
+    path: String,
+    options: backup::ImportOptions,
+) -> Result<backup::ImportReport, String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc","Importing library from {} with {:?}", path, options);
+    backup::import_library(&PathBuf::from(path), &options)
+        .await
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to import library: {:?}", e);
+            e.to_string()
+        })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn export_game(name: String, ids: Vec<String>) -> Result<Option<String>, String> {
+    info!(target:"rgsm::ipc","Exporting game {} with snapshots {:?}", name, ids);
+    let Some(path) = dialog::blocking::FileDialogBuilder::new()
+        .add_filter("RGSM Game", &["rgsm-game"])
+        .set_file_name(&[&name, ".rgsm-game"].concat())
+        .save_file()
+    else {
+        warn!(target:"rgsm::ipc", "User closed the dialog, export cancelled.");
+        return Ok(None);
+    };
+    backup::export_game(&name, &ids, &path).map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to export game: {:?}", e);
+        e.to_string()
+    })?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn import_game(
+    path: String,
+    options: backup::GameImportOptions,
+) -> Result<backup::GameImportReport, String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc","Importing game from {} with {:?}", path, options);
+    backup::import_game(&PathBuf::from(path), &options)
+        .await
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to import game: {:?}", e);
+            e.to_string()
+        })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn reindex_game(
+    game: Game,
+    adopt_extra_backups: bool,
+) -> Result<backup::ReindexReport, String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc","Reindexing game: {}", game.name);
+    backup::reindex_game(&game, adopt_extra_backups)
+        .await
+        .map_err(|e| {
+            error!(target:"rgsm::ipc", "Failed to reindex game: {:?}", e);
+            e.to_string()
+        })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn reindex_all(adopt_extra_backups: bool) -> Result<Vec<backup::ReindexReport>, String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc","Reindexing all games.");
+    backup::reindex_all(adopt_extra_backups).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to reindex all games: {:?}", e);
+        e.to_string()
+    })
+}
+
+#[allow(unused)]
+#[tauri::command]
+pub async fn check_repository(fix: bool) -> Result<backup::RepositoryReport, String> {
+    reject_in_safe_mode()?;
+    info!(target:"rgsm::ipc","Checking backup repository, fix: {}", fix);
+    backup::check_repository(fix).await.map_err(|e| {
+        error!(target:"rgsm::ipc", "Failed to check repository: {:?}", e);
+        e.to_string()
+    })
 }
 
 #[allow(unused)]
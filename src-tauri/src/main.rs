@@ -8,16 +8,19 @@ extern crate rust_i18n;
 use rust_i18n::t;
 i18n!("../locales", fallback = ["en_US", "zh_SIMPLIFIED"]);
 
-use config::{get_config, Config};
+use config::{app_paths, exe_dir, get_config, migrate_from, safe_mode_error, Config, ConfigStore};
 
+use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::api::notification::Notification;
-use tracing::info;
+use tracing::{error, info, warn};
 use tracing_subscriber::{filter::LevelFilter, Layer};
 
 use crate::config::config_check;
+use crate::errors::ConfigError;
 
 mod backup;
+mod cli;
 mod cloud_sync;
 mod config;
 mod default_value;
@@ -26,16 +29,49 @@ mod ipc_handler;
 mod quick_actions;
 mod traits;
 
+/// 将旧版本放在当前目录下的文件迁移到平台目录（只进行一次）
+fn migrate_paths() -> Result<Vec<PathBuf>, ConfigError> {
+    app_paths().create_dirs()?;
+    Ok(migrate_from(&exe_dir(), app_paths())?)
+}
+
 fn main() {
-    // Init config
-    if let Err(e) = config_check() {
-        panic!("Check on config file filed: {:?}", e);
+    // 命令行模式也要读取迁移后的配置
+    let migrated = migrate_paths();
+
+    // 命令行模式，执行完成后直接退出，不修改配置也不弹出通知
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
     }
-    let config = get_config().unwrap_or_else(|e| panic!("Cannot load config file: {:?}", e));
+
+    // Init config
+    // 配置无法使用时以安全模式启动，由前端提示用户恢复备份或重置
+    let migrated = migrated.and_then(|migrated| {
+        config_check()?;
+        Ok(migrated)
+    });
+    let migrated = migrated.unwrap_or_else(|e| {
+        config::enter_safe_mode(e.to_string());
+        Vec::new()
+    });
+    let config = get_config().unwrap_or_default();
 
     // Init log
     init_log(&config);
     info!("{}", t!("home.hello_world"));
+    if !migrated.is_empty() {
+        info!("Migrated files to platform directories: {:?}", migrated);
+    }
+    let safe_mode = safe_mode_error();
+    if let Some(e) = &safe_mode {
+        error!("Cannot load config, starting in safe mode: {}", e);
+    }
+
+    // 定期重试之前未能同步到云端的操作
+    if safe_mode.is_none() {
+        cloud_sync::start_replay_worker();
+    }
 
     // Init app
     let app = tauri::Builder::default()
@@ -43,14 +79,23 @@ fn main() {
             // 自动备份间隔，启动时默认为无（不自动备份）
             quick_actions::AutoBackupDuration::new(0),
         ))
+        .manage(cloud_sync::CloudJobs::default())
+        // 内存中的配置，变更时通知前端和托盘、快捷键
+        .manage(ConfigStore::global())
         .invoke_handler(tauri::generate_handler![
             ipc_handler::open_url,
+            ipc_handler::launch_game,
             ipc_handler::choose_save_file,
             ipc_handler::choose_save_dir,
             ipc_handler::get_local_config,
+            ipc_handler::get_config_read_only,
+            ipc_handler::get_startup_status,
+            ipc_handler::restore_config_backup,
+            ipc_handler::reset_config_file,
             ipc_handler::add_game,
             ipc_handler::restore_snapshot,
             ipc_handler::delete_snapshot,
+            ipc_handler::evict_local_snapshot,
             ipc_handler::delete_game,
             ipc_handler::get_game_snapshots_info,
             ipc_handler::set_config,
@@ -60,10 +105,34 @@ fn main() {
             ipc_handler::check_cloud_backend,
             ipc_handler::cloud_upload_all,
             ipc_handler::cloud_download_all,
+            ipc_handler::cancel_cloud_job,
+            ipc_handler::cloud_prune,
+            ipc_handler::get_sync_queue,
+            ipc_handler::retry_sync_queue,
+            ipc_handler::cloud_list_remote,
+            ipc_handler::cloud_delete_remote_snapshot,
+            ipc_handler::cloud_download_game,
+            ipc_handler::cloud_import_game,
             ipc_handler::set_snapshot_description,
+            ipc_handler::set_snapshot_pinned,
+            ipc_handler::add_snapshot_tags,
+            ipc_handler::remove_snapshot_tags,
+            ipc_handler::search_snapshots,
+            ipc_handler::get_snapshot_branches,
+            ipc_handler::get_branch_snapshots,
+            ipc_handler::set_branch_name,
+            ipc_handler::create_profile,
+            ipc_handler::switch_profile,
             ipc_handler::backup_all,
             ipc_handler::apply_all,
             ipc_handler::set_quick_backup_game,
+            ipc_handler::export_library,
+            ipc_handler::import_library,
+            ipc_handler::export_game,
+            ipc_handler::import_game,
+            ipc_handler::reindex_game,
+            ipc_handler::reindex_all,
+            ipc_handler::check_repository,
             ipc_handler::get_locale_message
         ]);
 
@@ -74,7 +143,14 @@ fn main() {
     let app = app
         .system_tray(quick_actions::get_tray())
         .on_system_tray_event(quick_actions::tray_event_handler)
-        .setup(quick_actions::setup);
+        .setup(move |app| {
+            ConfigStore::global().attach(app.handle());
+            // 安全模式下不启用快捷操作
+            if safe_mode.is_some() {
+                return Ok(());
+            }
+            quick_actions::setup(app)
+        });
 
     // 处理退出到托盘（关闭窗口不退出）
     if config.settings.exit_to_tray {
@@ -92,11 +168,13 @@ fn main() {
     }
 
     // 需要初始化Notification，否则第一次提示不会显示
-    Notification::new("Init Info")
+    if let Err(e) = Notification::new("Init Info")
         .title("Init")
         .body("Initiating notification module")
         .show()
-        .expect("Cannot show notification");
+    {
+        warn!("Cannot show notification: {:?}", e);
+    }
 }
 
 fn init_log(config: &Config) {
@@ -105,15 +183,16 @@ fn init_log(config: &Config) {
 
     let console_layer = fmt::layer().with_timer(time::LocalTime::rfc_3339());
 
-    if config.settings.log_to_file {
-        let file_appender = RollingFileAppender::builder()
+    // 日志目录不可写时只输出到控制台
+    let file_appender = config.settings.log_to_file.then(|| {
+        RollingFileAppender::builder()
             .rotation(Rotation::DAILY)
             .filename_prefix("RGSM")
             .filename_suffix("log")
             .max_log_files(3)
-            .build("./log")
-            .expect("initializing rolling file appender failed");
-
+            .build(app_paths().log_dir())
+    });
+    if let Some(Ok(file_appender)) = file_appender {
         let file_layer = fmt::layer()
             .with_timer(time::LocalTime::rfc_3339())
             .with_writer(file_appender)
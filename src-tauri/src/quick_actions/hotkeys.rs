@@ -1,48 +1,79 @@
-use tauri::App;
 use tauri::GlobalShortcutManager;
-use tracing::info;
+use tauri::{App, AppHandle};
+use tracing::{error, info};
 
 use super::*;
 use crate::config::Config;
 
-pub fn setup_hotkeys(config: &Config, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
-    info!(target:"rgsm::quick_action::hotkeys", "Setting up hotkeys");
-    let mut manager = app.global_shortcut_manager();
+/// 将按键组合转换为快捷键字符串，未设置时为空
+fn join_keys(keys: &[String]) -> String {
+    let mut keys = keys.to_vec();
+    keys.retain(|x| !x.is_empty());
+    keys.join("+")
+}
 
-    let mut apply_keys = config.quick_action.hotkeys.apply.clone();
-    apply_keys.retain(|x| !x.is_empty());
-    let key_string = apply_keys.join("+");
+fn register_hotkeys<M: GlobalShortcutManager>(
+    manager: &mut M,
+    config: &Config,
+) -> Result<(), tauri::Error> {
+    let key_string = join_keys(&config.quick_action.hotkeys.apply);
     if !key_string.is_empty() {
         info!(
             target:"rgsm::quick_action::hotkeys",
             "Registering apply hotkey: {}", key_string
         );
-        manager
-            .register(&key_string, move || {
-                tauri::async_runtime::spawn(async move {
-                    quick_apply(QuickActionType::Hotkey).await;
-                });
-            })
-            .expect("Cannot setup hotkeys");
+        manager.register(&key_string, move || {
+            tauri::async_runtime::spawn(async move {
+                quick_apply(QuickActionType::Hotkey).await;
+            });
+        })?;
     }
 
-    let mut backup_keys = config.quick_action.hotkeys.backup.clone();
-    backup_keys.retain(|x| !x.is_empty());
-    let key_string = backup_keys.join("+");
+    let key_string = join_keys(&config.quick_action.hotkeys.backup);
     if !key_string.is_empty() {
         info!(
             target:"rgsm::quick_action::hotkeys",
             "Registering backup hotkey: {}", key_string
         );
-        manager
-            .register(&key_string, move || {
-                tauri::async_runtime::spawn(async move {
-                    quick_backup(QuickActionType::Hotkey).await;
-                });
-            })
-            .expect("Cannot setup hotkeys");
+        manager.register(&key_string, move || {
+            tauri::async_runtime::spawn(async move {
+                quick_backup(QuickActionType::Hotkey).await;
+            });
+        })?;
     }
+    Ok(())
+}
 
-    info!(target:"rgsm::quick_action::hotkeys","All hotkey are registered.");
+pub fn setup_hotkeys(config: &Config, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+    info!(target:"rgsm::quick_action::hotkeys", "Setting up hotkeys");
+    let mut manager = app.global_shortcut_manager();
+    // 快捷键被其他程序占用时不影响启动
+    match register_hotkeys(&mut manager, config) {
+        Ok(_) => info!(target:"rgsm::quick_action::hotkeys","All hotkey are registered."),
+        Err(e) => {
+            error!(target:"rgsm::quick_action::hotkeys", "Cannot register hotkeys: {:?}", e)
+        }
+    }
     Ok(())
 }
+
+/// 配置中的快捷键变更后，注销旧的快捷键并注册新的
+pub fn update_hotkeys(app_handle: &AppHandle, old: &Config, new: &Config) {
+    let (old_keys, new_keys) = (&old.quick_action.hotkeys, &new.quick_action.hotkeys);
+    if old_keys.apply == new_keys.apply && old_keys.backup == new_keys.backup {
+        return;
+    }
+    info!(target:"rgsm::quick_action::hotkeys", "Hotkeys changed, registering again");
+    let mut manager = app_handle.global_shortcut_manager();
+    for keys in [&old_keys.apply, &old_keys.backup] {
+        let key_string = join_keys(keys);
+        if !key_string.is_empty() {
+            if let Err(e) = manager.unregister(&key_string) {
+                error!(target:"rgsm::quick_action::hotkeys", "Cannot unregister hotkey {}: {:?}", key_string, e);
+            }
+        }
+    }
+    if let Err(e) = register_hotkeys(&mut manager, new) {
+        error!(target:"rgsm::quick_action::hotkeys", "Cannot register hotkeys: {:?}", e);
+    }
+}
@@ -7,4 +7,4 @@ use utils::*;
 
 pub use timer::AutoBackupDuration;
 pub use tray::{get_tray, tray_event_handler};
-pub use utils::{set_current_game, setup};
+pub use utils::{set_current_game, setup, QuickActionType};
@@ -4,17 +4,21 @@ use tauri::{
     utils::config::WindowConfig, AppHandle, CustomMenuItem, LogicalSize, Manager, State,
     SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu,
 };
-use tracing::info;
+use tracing::{error, info};
 
-use crate::config::get_config;
+use crate::config::{get_config, safe_mode_error, Config};
 
 use super::{quick_apply, quick_backup, AutoBackupDuration, QuickActionType};
 
 use rust_i18n::t;
 
-// TODO:处理错误
 pub fn get_tray() -> SystemTray {
-    let config = get_config().expect("Cannot get config");
+    let quit = CustomMenuItem::new("quit".to_owned(), t!("backend.tray.exit"));
+    // 安全模式下不提供快捷操作
+    if safe_mode_error().is_some() {
+        return SystemTray::new().with_menu(SystemTrayMenu::new().add_item(quit));
+    }
+    let config = get_config().unwrap_or_default();
     let current_quick_action_game = match config.quick_action.quick_action_game {
         Some(game) => CustomMenuItem::new("game".to_owned(), game.name),
         None => CustomMenuItem::new("game".to_owned(), t!("backend.tray.no_game_selected")),
@@ -55,43 +59,61 @@ pub fn get_tray() -> SystemTray {
             t!("backend.tray.quick_apply"),
         ))
         .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(CustomMenuItem::new(
-            "quit".to_owned(),
-            t!("backend.tray.exit"),
-        ));
+        .add_item(quit);
     // Menu items end
 
     SystemTray::new().with_menu(tray_menu)
 }
 
+/// 当前快捷操作的游戏变更后更新托盘中显示的游戏名
+pub fn update_tray(app_handle: &AppHandle, old: &Config, new: &Config) {
+    let name = |config: &Config| {
+        config
+            .quick_action
+            .quick_action_game
+            .as_ref()
+            .map(|game| game.name.clone())
+    };
+    let current = name(new);
+    if name(old) == current {
+        return;
+    }
+    let title = current.unwrap_or_else(|| t!("backend.tray.no_game_selected").to_string());
+    if let Err(e) = app_handle.tray_handle().get_item("game").set_title(title) {
+        error!(target:"rgsm::quick_action::tray", "Cannot update tray title: {:?}", e);
+    }
+}
+
+/// 主窗口存在时关闭，否则重新创建
+fn toggle_main_window(app: &AppHandle) -> Result<(), tauri::Error> {
+    if let Some(window) = app.get_window("main") {
+        return window.close();
+    }
+    let window = tauri::WindowBuilder::from_config(
+        app,
+        WindowConfig {
+            label: "main".to_string(),
+            url: tauri::WindowUrl::App("index.html".into()),
+            file_drop_enabled: false, // 必须这样设置，否则窗体内js接收不到drag & drop事件
+            title: "RustyManager".to_string(),
+            ..Default::default()
+        },
+    )
+    .build()?;
+    window.set_size(LogicalSize {
+        width: 1280.0,
+        height: 720.0,
+    })?;
+    window.show()?;
+    window.set_focus()
+}
+
 pub fn tray_event_handler(app: &AppHandle, event: SystemTrayEvent) {
     match event {
         SystemTrayEvent::LeftClick { .. } => {
             info!(target: "rgsm::quick_action::tray", "Tray left click");
-            if let Some(window) = app.get_window("main") {
-                window.close().expect("Cannot close window");
-            } else {
-                let window = tauri::WindowBuilder::from_config(
-                    app,
-                    WindowConfig {
-                        label: "main".to_string(),
-                        url: tauri::WindowUrl::App("index.html".into()),
-                        file_drop_enabled: false, // 必须这样设置，否则窗体内js接收不到drag & drop事件
-                        title: "RustyManager".to_string(),
-                        ..Default::default()
-                    },
-                )
-                .build()
-                .unwrap();
-
-                window
-                    .set_size(LogicalSize {
-                        width: 1280.0,
-                        height: 720.0,
-                    })
-                    .expect("Cannot set size");
-                window.show().expect("Cannot show window");
-                window.set_focus().expect("Cannot set focus");
+            if let Err(e) = toggle_main_window(app) {
+                error!(target: "rgsm::quick_action::tray", "Cannot toggle main window: {:?}", e);
             }
         }
         SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
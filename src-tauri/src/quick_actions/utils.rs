@@ -1,23 +1,22 @@
-use tauri::{api::notification::Notification, AppHandle};
+use tauri::api::notification::Notification;
 use tracing::{error, info, warn};
 
 use crate::{
     backup::Game,
-    config::{get_config, set_config},
-    errors::BackupError,
+    config::{get_config, update_config, ConfigStore},
+    errors::{BackupError, ConfigError},
 };
 
 use super::*;
 
-pub async fn set_current_game(app: &AppHandle, game: Game) {
+pub async fn set_current_game(game: Game) -> Result<(), ConfigError> {
     info!(target:"rgsm::tray","Setting current quick backup game:{}",game.name);
-    app.tray_handle()
-        .get_item("game")
-        .set_title(&game.name)
-        .expect("Cannot get tray handle");
-    let mut config = get_config().expect("Cannot get config");
-    config.quick_action.quick_action_game = Some(game);
-    set_config(&config).await.expect("Cannot set config");
+    // 托盘中的游戏名会在配置变更时更新
+    update_config(|config| {
+        config.quick_action.quick_action_game = Some(game);
+        Ok(())
+    })
+    .await
 }
 
 #[derive(Debug, PartialEq)]
@@ -40,24 +39,19 @@ impl QuickActionType {
 pub async fn quick_apply(t: QuickActionType) {
     info!(target:"rgsm::quick_action", "Auto apply triggered: {:#?}",t.generate_describe());
     let game = get_quick_action_game();
-    // 这里使用立即执行的闭包是为了做错误处理
-    let result: Result<(), BackupError> = (|| {
+    let result: Result<(), BackupError> = async {
         match &game {
             Some(game) => {
                 info!(target:"rgsm::quick_action", "Quick apply game: {:#?}", game);
-                let newest_date = game
-                    .get_game_snapshots_info()?
-                    .backups
-                    .last()
-                    .ok_or(BackupError::NoBackupAvailable)?
-                    .date
-                    .clone();
-                game.restore_snapshot(&newest_date, None)?;
+                // 只恢复当前档案的存档
+                let newest = game.latest_snapshot()?;
+                game.restore_snapshot(&newest, None).await?;
             }
             None => show_no_game_selected_error(),
         };
         Ok(())
-    })();
+    }
+    .await;
     match result {
         Err(e) => {
             error!(target:"rgsm::quick_action", "Quick apply failed: {:#?}", &e);
@@ -84,14 +78,14 @@ pub async fn quick_backup(t: QuickActionType) {
     info!(target:"rgsm::quick_action", "Auto backup triggered: {:#?}",t.generate_describe());
     let game = get_quick_action_game();
     let show_info = get_config()
-        .expect("Cannot get config")
-        .settings
-        .prompt_when_auto_backup;
+        .map(|config| config.settings.prompt_when_auto_backup)
+        .unwrap_or(true);
     let result: Result<(), BackupError> = async {
         match &game {
             None => show_no_game_selected_error(),
             Some(game) => {
-                game.create_snapshot(&t.generate_describe()).await?;
+                game.create_snapshot(&t.generate_describe(), (&t).into())
+                    .await?;
             }
         };
         Ok(())
@@ -125,11 +119,13 @@ pub async fn quick_backup(t: QuickActionType) {
 }
 
 fn show_notification<T1: Into<String>, T2: Into<String>>(title: T1, body: T2) {
-    Notification::new("QuickAction")
+    if let Err(e) = Notification::new("QuickAction")
         .title(title)
         .body(body)
         .show()
-        .expect("Cannot show notification");
+    {
+        error!(target:"rgsm::quick_action", "Cannot show notification: {:?}", e);
+    }
 }
 
 fn show_no_game_selected_error() {
@@ -141,16 +137,24 @@ fn show_no_game_selected_error() {
 }
 
 pub fn get_quick_action_game() -> Option<Game> {
-    get_config()
-        .expect("Cannot get config")
-        .quick_action
-        .quick_action_game
-        .clone()
+    match get_config() {
+        Ok(config) => config.quick_action.quick_action_game,
+        Err(e) => {
+            error!(target:"rgsm::quick_action", "Cannot get config: {:?}", e);
+            None
+        }
+    }
 }
 
 pub fn setup(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     let config = get_config()?;
     timer::setup_timer(app)?;
     hotkeys::setup_hotkeys(&config, app)?;
+    // 配置变更时立即更新托盘和快捷键，无需重启
+    let app_handle = app.handle();
+    ConfigStore::global().subscribe(move |old, new| {
+        tray::update_tray(&app_handle, old, new);
+        hotkeys::update_hotkeys(&app_handle, old, new);
+    });
     Ok(())
 }
@@ -1,3 +1,5 @@
+import type { Backup } from "./saveTypes";
+
 /**
  * 所有可能的后端类型
  */
@@ -21,4 +23,133 @@ export type S3 = {
     region: string,
     access_key_id: string,
     secret_access_key: string,
-}
\ No newline at end of file
+}
+
+/**
+ * 云端的单个存档
+ */
+export type RemoteSnapshot = {
+    id: string,
+    date?: string,
+    describe?: string,
+    size: number,
+    last_modified?: string,
+}
+
+/**
+ * 云端的一个游戏目录
+ */
+export type RemoteGame = {
+    name: string,
+    snapshot_count: number,
+    total_size: number,
+    last_modified?: string,
+    in_local_config: boolean,
+    snapshots: Array<RemoteSnapshot>,
+}
+
+/**
+ * 等待同步到云端的操作
+ */
+export type PendingOperation =
+    | { type: "UploadGame", game: string }
+    | { type: "DeleteSnapshot", game: string, id: string }
+    | { type: "DeleteGame", game: string }
+    | { type: "UploadConfig" }
+
+export type JournalEntry = {
+    id: number,
+    operation: PendingOperation,
+    created_at: string,
+    attempts: number,
+    last_error?: string,
+}
+
+/**
+ * 启动状态，配置无法使用时处于安全模式
+ */
+export type StartupStatus = {
+    safe_mode_error?: string,
+    backup_available: boolean,
+}
+
+/**
+ * 导入整个库时的选项
+ */
+export type PathMapping = {
+    from: string,
+    to: string,
+}
+
+export type ImportOptions = {
+    path_mappings: Array<PathMapping>,
+    rename_on_conflict: boolean,
+    import_settings: boolean,
+}
+
+export type ImportReport = {
+    imported: Array<{ original_name: string, name: string }>,
+    skipped: Array<string>,
+}
+
+/**
+ * 导入单个游戏文件的结果
+ */
+export type GameImportReport = {
+    name: string,
+    missing_paths: Array<string>,
+    imported_snapshots: number,
+}
+
+/**
+ * 按备份文件夹重建存档列表的结果
+ */
+export type ReindexReport = {
+    game: string,
+    added: Array<string>,
+    missing: Array<string>,
+    cloud_only: Array<string>,
+    adopted: Array<string>,
+    restored: Array<string>,
+    rebuilt: boolean,
+}
+
+/**
+ * 检查备份目录的结果，`fix` 为空的问题需要手动处理
+ */
+export type RepositoryReport = {
+    games_checked: number,
+    findings: Array<{
+        issue: { kind: string, game?: string, folder?: string, id?: string, error?: string },
+        fix: "remove_entry" | "reindex" | null,
+        fixed: boolean,
+    }>,
+}
+
+/**
+ * 跨游戏搜索存档的条件，未填写的条件不做限制
+ */
+export type SnapshotQuery = {
+    text?: string,
+    tags?: Array<string>,
+    from?: number,
+    to?: number,
+    triggers?: Array<Backup["trigger"]>,
+    games?: Array<string>,
+}
+
+export type SnapshotSearchResult = {
+    game: string,
+    snapshot: Backup,
+}
+
+/**
+ * 存档的分支，恢复旧存档后继续备份会从该存档分出新的分支
+ */
+export type SnapshotBranch = {
+    id: string,
+    name: string | null,
+    fork_point: string | null,
+    snapshots: Array<string>,
+    children: Array<SnapshotBranch>,
+}
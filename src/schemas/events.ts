@@ -6,4 +6,16 @@ export interface IpcNotification {
     level: NotificationLevel,
     title: string,
     msg: string,
-}
\ No newline at end of file
+}
+export interface CloudProgress {
+    job_id: string,
+    direction: "Upload" | "Download",
+    game?: string,
+    current?: string,
+    transferred_bytes: number,
+    total_bytes: number,
+    finished_objects: number,
+    total_objects: number,
+    per_game_bytes: Record<string, number>,
+    done: boolean,
+}
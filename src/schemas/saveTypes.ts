@@ -19,9 +19,21 @@ export interface Game {
 
 export interface Backup {
     /**
-     * 存档的日期时间(和Saves中的游戏名可确定唯一存档)
+     * 存档的唯一标识(和Saves中的游戏名可确定唯一存档)，也是压缩包的文件名
+     */
+    id: string;
+    /**
+     * 存档创建时的本地时间，仅用于显示
      */
     date: string;
+    /**
+     * 存档的创建时间，Unix 时间戳（毫秒）
+     */
+    timestamp: number;
+    /**
+     * 创建存档时所在时区与 UTC 的偏移，如 +08:00
+     */
+    timezone: string;
     /**
      * 对当前存档的描述性文本
      */
@@ -30,8 +42,51 @@ export interface Backup {
      * 当前存档压缩包存放的路径
      */
     path: string;
+    /**
+     * 存档压缩包的存放位置，仅在云端的存档会在恢复时下载
+     */
+    storage: "Local" | "Cloud" | "Both";
+    /**
+     * 置顶的存档不会被保留策略清理
+     */
+    pinned: boolean;
+    /**
+     * 云端的保留策略已删除该存档，不再上传
+     */
+    remote_expired: boolean;
+    /**
+     * 压缩包的大小（字节），旧版本的存档为 0
+     */
+    size: number;
+    /**
+     * 压缩前所有文件的大小（字节）
+     */
+    uncompressed_size: number;
+    file_count: number;
+    /**
+     * 创建存档的方式
+     */
+    trigger: "Unknown" | "Manual" | "Timer" | "Tray" | "Hotkey" | "PreRestore" | "Launcher" | "ProfileSwitch";
+    /**
+     * 创建存档的设备名和软件版本
+     */
+    device: string;
+    app_version: string;
+    tags: Array<string>;
+    /**
+     * 创建时所基于的存档，为空时是分支的起点
+     */
+    parent: string | null;
+    /**
+     * 所属的存档档案，为空时属于默认档案
+     */
+    profile: string | null;
 }
 export interface BackupsInfo {
+    /**
+     * Backups.json 的格式版本
+     */
+    version: number;
     /**
      * 游戏名(判断存档组的唯一标识)
      */
@@ -40,6 +95,22 @@ export interface BackupsInfo {
      * 存档信息
      */
     backups: Array<Backup>;
+    /**
+     * 最后恢复或创建的存档
+     */
+    head: string | null;
+    /**
+     * 分支的名称，以分支中第一个存档的 id 作为键
+     */
+    branch_names: Record<string, string>;
+    /**
+     * 默认档案以外的存档档案
+     */
+    profiles: Array<string>;
+    /**
+     * 当前使用的档案，为空时是默认档案
+     */
+    active_profile: string | null;
 }
 
 export interface CloudSettings {
@@ -59,6 +130,41 @@ export interface CloudSettings {
      * 同步的后端设置
      */
     backend: Backend;
+    /**
+     * 云端加密设置
+     */
+    encryption: EncryptionSettings;
+    /**
+     * 云端凭据的存放位置，System为系统密钥环(不可用时为本地加密文件)
+     */
+    secret_storage: "Config" | "System";
+    /**
+     * 上传限速，单位KiB/s，为0则不限速
+     */
+    upload_limit: number;
+    /**
+     * 下载限速，单位KiB/s，为0则不限速
+     */
+    download_limit: number;
+    /**
+     * 列表中的游戏运行时暂停随时同步
+     */
+    pause_while_playing: boolean;
+    /**
+     * 云端保留的存档数量，为0则全部保留
+     */
+    remote_retention: number;
+}
+
+export interface EncryptionSettings {
+    /**
+     * 是否在上传前加密
+     */
+    enabled: boolean;
+    /**
+     * 加密口令，各设备需保持一致
+     */
+    passphrase: string;
 }
 
 export interface Settings {
@@ -110,6 +216,10 @@ export interface Settings {
      * 是否将新游戏默认加入收藏夹
      */
     add_new_to_favorites: boolean,
+    /**
+     * 本地保留的存档数量，为0则全部保留
+     */
+    local_retention: number,
 }
 
 export interface FavoriteTreeNode {
@@ -187,7 +297,16 @@ export let default_config: Config = {
             root_path: "/game-save-manager",
             backend: {
                 type: "Disabled",
-            }
+            },
+            encryption: {
+                enabled: false,
+                passphrase: "",
+            },
+            secret_storage: "Config",
+            upload_limit: 0,
+            download_limit: 0,
+            pause_while_playing: false,
+            remote_retention: 0,
         },
         prompt_when_auto_backup: false,
         locale: "zh_SIMPLIFIED",
@@ -195,7 +314,8 @@ export let default_config: Config = {
         default_expend_favorites_tree: false,
         home_page: "/home",
         log_to_file: true,
-        add_new_to_favorites: false
+        add_new_to_favorites: false,
+        local_retention: 0
     },
     favorites: [],
     quick_action: {
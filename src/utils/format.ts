@@ -0,0 +1,12 @@
+/**
+ * 将字节数格式化为便于阅读的大小
+ */
+export function format_size(size: number) {
+    const units = ["B", "KB", "MB", "GB"]
+    let i = 0
+    while (size >= 1024 && i < units.length - 1) {
+        size /= 1024
+        i++
+    }
+    return `${size.toFixed(i == 0 ? 0 : 1)} ${units[i]}`
+}